@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde_json::{json, Map, Value};
+
+use crate::constants::{
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_YES, CHECKPOINT_FILE,
+    CONTENT_SHA256, DATE_OF_VOTING, DOMESTIC_VOTERS, FETCHED_AT, INVALID_VOTING_BALLOTS, KIND, NO,
+    OUTCOME, OVERSEAS_VOTERS, PARTICIPATION, RATIO_NO, RATIO_YES, RECOMMENDATION, RESULT_STATUS,
+    SOURCE_URL_DE, SOURCE_URL_FR, SOURCE_URL_IT, STATUS, SUPPLEMENTARY_URL, TITLE_DE, TITLE_FR,
+    TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALIDATED_ON, VALID_VOTING_BALLOTS,
+};
+use crate::data::{Outcome, ResultStatus, Row, Tristate, VotingStatus};
+use crate::extractors::ParsingIssues;
+
+// Keys of the cells that could not be used, next to the columns of the row
+const UNPARSED_CELLS: &str = "unparsed_cells";
+const UNKNOWN_LABELS: &str = "unknown_labels";
+
+// Rows parsed so far, one JSON object per line, so that a scrape that died can be resumed. All
+// values are written as strings to keep them exactly as parsed, e.g. the scale of decimals.
+pub struct Checkpoint {
+    file: File,
+    parsed_rows: HashMap<String, (Row, ParsingIssues)>,
+}
+
+impl Checkpoint {
+    pub fn open(resume: bool) -> Checkpoint {
+        Checkpoint::open_at(CHECKPOINT_FILE, resume)
+    }
+
+    fn open_at(path: &str, resume: bool) -> Checkpoint {
+        if !resume {
+            let file = File::create(path).expect("could not create file");
+            return Checkpoint {
+                file,
+                parsed_rows: HashMap::new(),
+            };
+        }
+
+        let parsed_rows = read_rows(path);
+        info!(
+            "Resuming with {} votings of the checkpoint",
+            parsed_rows.len()
+        );
+
+        // New rows are appended, so that the resumed ones are kept if this run dies as well
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .expect("could not open file");
+        // The last line is completed if the scrape died while writing it
+        let is_complete = fs::read(path)
+            .map(|content| content.last().is_none_or(|byte| *byte == b'\n'))
+            .unwrap_or(true);
+        if !is_complete {
+            writeln!(file).expect("Could not write the checkpoint");
+        }
+        Checkpoint { file, parsed_rows }
+    }
+
+    // A checkpoint is left behind by a run that did not complete
+    pub fn exists() -> bool {
+        Path::new(CHECKPOINT_FILE).exists()
+    }
+
+    pub fn take_parsed_row(&mut self, url: &str) -> Option<(Row, ParsingIssues)> {
+        self.parsed_rows.remove(url)
+    }
+
+    pub fn save(&mut self, row: &Row, issues: &ParsingIssues) {
+        writeln!(self.file, "{}", line_of(row, issues)).expect("Could not write the checkpoint");
+        self.file.flush().expect("Could not write the checkpoint");
+    }
+
+    pub fn remove() {
+        if let Err(error) = fs::remove_file(CHECKPOINT_FILE) {
+            warn!("Could not remove the checkpoint: {}", error);
+        }
+    }
+}
+
+fn line_of(row: &Row, issues: &ParsingIssues) -> String {
+    let mut object = row_to_json(row);
+    let fields = object.as_object_mut().unwrap();
+    fields.insert(UNPARSED_CELLS.to_string(), json!(issues.unparsed_cells));
+    fields.insert(UNKNOWN_LABELS.to_string(), json!(issues.unknown_labels));
+    object.to_string()
+}
+
+fn read_rows(path: &str) -> HashMap<String, (Row, ParsingIssues)> {
+    let Ok(file) = OpenOptions::new().read(true).open(path) else {
+        warn!("No checkpoint found, all votings are parsed");
+        return HashMap::new();
+    };
+
+    let mut rows: HashMap<String, (Row, ParsingIssues)> = HashMap::new();
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        // The last line is incomplete if the scrape died while writing it
+        let row = line
+            .ok()
+            .and_then(|line| serde_json::from_str::<Map<String, Value>>(&line).ok())
+            .and_then(|object| Some((row_from_json(&object)?, issues_from_json(&object)?)));
+        match row {
+            Some((row, issues)) => {
+                rows.insert(row.source_url_it.clone(), (row, issues));
+            }
+            None => warn!("Ignored invalid line {} of the checkpoint", idx + 1),
+        }
+    }
+    rows
+}
+
+fn optional<T: Display>(value: &Option<T>) -> Value {
+    match value {
+        Some(value) => Value::String(value.to_string()),
+        None => Value::Null,
+    }
+}
+
+fn row_to_json(row: &Row) -> Value {
+    let mut object = json!({
+        NO: optional(&row.no),
+        DATE_OF_VOTING: optional(&row.date_of_voting),
+        TITLE_IT: row.title_it,
+        TITLE_FR: row.title_fr,
+        TITLE_DE: row.title_de,
+        KIND: row.kind,
+        RECOMMENDATION: row.recommendation,
+        TOTAL_VOTERS: optional(&row.total_voters),
+        DOMESTIC_VOTERS: optional(&row.domestic_voters),
+        BALLOTS_RETURNED: optional(&row.ballots_returned),
+        PARTICIPATION: optional(&row.participation),
+        INVALID_VOTING_BALLOTS: optional(&row.invalid_voting_ballots),
+        BLANK_VOTING_BALLOTS: optional(&row.blank_voting_ballots),
+        VALID_VOTING_BALLOTS: optional(&row.valid_voting_ballots),
+        TOTAL_YES: optional(&row.total_yes),
+        RATIO_YES: optional(&row.ratio_yes),
+        TOTAL_NO: optional(&row.total_no),
+        RATIO_NO: optional(&row.ratio_no),
+        OUTCOME: optional(&row.outcome),
+        STATUS: row.status.as_str(),
+        RESULT_STATUS: optional(&row.result_status),
+        VALIDATED_ON: optional(&row.validated_on),
+        SOURCE_URL_IT: row.source_url_it,
+        SOURCE_URL_DE: row.source_url_de,
+        SOURCE_URL_FR: row.source_url_fr,
+        SUPPLEMENTARY_URL: row.supplementary_url,
+        FETCHED_AT: optional(
+            &row.fetched_at
+                .map(|fetched_at| fetched_at.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        ),
+        CONTENT_SHA256: row.content_sha256,
+    });
+    let fields = object.as_object_mut().unwrap();
+    insert_tristate(fields, OVERSEAS_VOTERS, &row.overseas_voters);
+    insert_tristate(fields, CANTONS_VOTING_YES, &row.cantons_voting_yes);
+    insert_tristate(fields, CANTONS_VOTING_NO, &row.cantons_voting_no);
+    object
+}
+
+fn insert_tristate<T: Copy + Display>(
+    object: &mut Map<String, Value>,
+    column: &str,
+    value: &Tristate<T>,
+) {
+    object.insert(column.to_string(), optional(&value.value()));
+    object.insert(format!("{}_status", column), json!(value.status()));
+}
+
+fn text(object: &Map<String, Value>, column: &str) -> Option<Option<String>> {
+    match object.get(column)? {
+        Value::String(value) => Some(Some(value.clone())),
+        Value::Null => Some(None),
+        _ => None,
+    }
+}
+
+fn parse<T: FromStr>(object: &Map<String, Value>, column: &str) -> Option<Option<T>> {
+    match text(object, column)? {
+        Some(value) => Some(Some(value.parse::<T>().ok()?)),
+        None => Some(None),
+    }
+}
+
+fn tristate<T: Copy + FromStr>(object: &Map<String, Value>, column: &str) -> Option<Tristate<T>> {
+    let status = text(object, &format!("{}_status", column))?;
+    Some(Tristate::from_status(
+        parse(object, column)?,
+        status.as_deref(),
+    ))
+}
+
+fn row_from_json(object: &Map<String, Value>) -> Option<Row> {
+    Some(Row {
+        no: parse(object, NO)?,
+        date_of_voting: parse::<NaiveDate>(object, DATE_OF_VOTING)?,
+        title_it: text(object, TITLE_IT)??,
+        title_fr: text(object, TITLE_FR)??,
+        title_de: text(object, TITLE_DE)??,
+        kind: text(object, KIND)??,
+        recommendation: text(object, RECOMMENDATION)?,
+        total_voters: parse(object, TOTAL_VOTERS)?,
+        domestic_voters: parse(object, DOMESTIC_VOTERS)?,
+        overseas_voters: tristate(object, OVERSEAS_VOTERS)?,
+        ballots_returned: parse(object, BALLOTS_RETURNED)?,
+        participation: parse::<Decimal>(object, PARTICIPATION)?,
+        invalid_voting_ballots: parse(object, INVALID_VOTING_BALLOTS)?,
+        blank_voting_ballots: parse(object, BLANK_VOTING_BALLOTS)?,
+        valid_voting_ballots: parse(object, VALID_VOTING_BALLOTS)?,
+        total_yes: parse(object, TOTAL_YES)?,
+        ratio_yes: parse::<Decimal>(object, RATIO_YES)?,
+        total_no: parse(object, TOTAL_NO)?,
+        ratio_no: parse::<Decimal>(object, RATIO_NO)?,
+        cantons_voting_yes: tristate::<Decimal>(object, CANTONS_VOTING_YES)?,
+        cantons_voting_no: tristate::<Decimal>(object, CANTONS_VOTING_NO)?,
+        outcome: parse::<Outcome>(object, OUTCOME)?,
+        status: parse::<VotingStatus>(object, STATUS)??,
+        result_status: parse::<ResultStatus>(object, RESULT_STATUS)?,
+        validated_on: parse::<NaiveDate>(object, VALIDATED_ON)?,
+        source_url_it: text(object, SOURCE_URL_IT)??,
+        source_url_de: text(object, SOURCE_URL_DE)??,
+        source_url_fr: text(object, SOURCE_URL_FR)??,
+        supplementary_url: text(object, SUPPLEMENTARY_URL)?,
+        fetched_at: parse::<DateTime<Utc>>(object, FETCHED_AT)?,
+        content_sha256: text(object, CONTENT_SHA256)??,
+    })
+}
+
+fn texts(object: &Map<String, Value>, key: &str) -> Option<Vec<String>> {
+    object
+        .get(key)?
+        .as_array()?
+        .iter()
+        .map(|value| value.as_str().map(str::to_string))
+        .collect()
+}
+
+fn issues_from_json(object: &Map<String, Value>) -> Option<ParsingIssues> {
+    Some(ParsingIssues {
+        unparsed_cells: texts(object, UNPARSED_CELLS)?,
+        unknown_labels: texts(object, UNKNOWN_LABELS)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn rows_are_read_back_as_written() {
+        let row = Row {
+            no: Some(631),
+            date_of_voting: NaiveDate::from_ymd_opt(2020, 9, 27),
+            title_it: "Iniziativa popolare «Per un'immigrazione moderata»".to_string(),
+            title_de: "Volksinitiative «Für eine massvolle Zuwanderung»".to_string(),
+            kind: "popular initiative".to_string(),
+            total_voters: Some(5_479_123),
+            domestic_voters: Some(5_285_663),
+            overseas_voters: Tristate::Value(193_460),
+            participation: Some(Decimal::new(5952, 4)),
+            ratio_yes: Some(Decimal::new(3830, 4)),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            cantons_voting_no: Tristate::Missing,
+            outcome: Some(Outcome::Rejected),
+            result_status: Some(ResultStatus::Final),
+            validated_on: NaiveDate::from_ymd_opt(2020, 11, 4),
+            source_url_it: "https://www.bk.admin.ch/ch/i/pore/va/20200927/det631.html".to_string(),
+            fetched_at: Some(Utc.with_ymd_and_hms(2020, 9, 28, 6, 30, 0).unwrap()),
+            content_sha256: "0".repeat(64),
+            ..Default::default()
+        };
+        let json = row_to_json(&row);
+        let read = row_from_json(json.as_object().unwrap()).unwrap();
+        assert_eq!(read, row);
+        // The scale of the decimals is kept, e.g. 0.3830 and not 0.383
+        assert_eq!(read.ratio_yes.unwrap().to_string(), "0.3830");
+    }
+
+    #[test]
+    fn rows_with_not_applicable_values_are_read_back_as_written() {
+        let row = Row {
+            date_of_voting: NaiveDate::from_ymd_opt(1970, 6, 7),
+            overseas_voters: Tristate::NotApplicable,
+            cantons_voting_no: Tristate::NotApplicable,
+            status: VotingStatus::Scheduled,
+            ..Default::default()
+        };
+        let json = row_to_json(&row);
+        assert_eq!(row_from_json(json.as_object().unwrap()).unwrap(), row);
+    }
+
+    fn row(no: u32) -> Row {
+        Row {
+            no: Some(no),
+            source_url_it: format!(
+                "https://www.bk.admin.ch/ch/i/pore/va/20200927/det{}.html",
+                no
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn checkpoint(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.jsonl", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn a_resumed_run_that_fails_early_keeps_the_rows() {
+        let path = checkpoint("checkpoint-resumed");
+        let mut first_run = Checkpoint::open_at(&path, false);
+        first_run.save(&row(631), &ParsingIssues::default());
+        first_run.save(&row(632), &ParsingIssues::default());
+        drop(first_run);
+
+        // The second run dies before it parsed any voting, e.g. on the summary page
+        drop(Checkpoint::open_at(&path, true));
+
+        let mut third_run = Checkpoint::open_at(&path, true);
+        assert_eq!(third_run.parsed_rows.len(), 2);
+        let (resumed, _) = third_run.take_parsed_row(&row(631).source_url_it).unwrap();
+        assert_eq!(resumed, row(631));
+        third_run.save(&row(633), &ParsingIssues::default());
+        drop(third_run);
+
+        assert_eq!(read_rows(&path).len(), 3);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_row_written_when_the_scrape_died_is_ignored() {
+        let path = checkpoint("checkpoint-incomplete");
+        let line = line_of(&row(631), &ParsingIssues::default());
+        fs::write(&path, format!("{}\n{}", line, &line[..line.len() / 2])).unwrap();
+
+        let mut resumed = Checkpoint::open_at(&path, true);
+        assert_eq!(resumed.parsed_rows.len(), 1);
+        resumed.save(&row(632), &ParsingIssues::default());
+        drop(resumed);
+
+        let rows = read_rows(&path);
+        assert_eq!(rows.len(), 2);
+        assert!(rows.contains_key(&row(632).source_url_it));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn issues_are_read_back_as_written() {
+        let issues = ParsingIssues {
+            unparsed_cells: vec![TOTAL_YES.to_string()],
+            unknown_labels: vec!["Totale dei voti espressi".to_string()],
+        };
+        let object = json!({
+            UNPARSED_CELLS: issues.unparsed_cells,
+            UNKNOWN_LABELS: issues.unknown_labels,
+        });
+        assert_eq!(issues_from_json(object.as_object().unwrap()), Some(issues));
+    }
+}
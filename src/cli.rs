@@ -0,0 +1,168 @@
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser, Debug)]
+#[command(about = "Create the dataset 'Swiss Popular Voting'")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Write percentages as published on the Chancellery pages, together with their exact
+    /// fraction, instead of floating point fractions
+    #[arg(long)]
+    pub decimal_ratios: bool,
+
+    /// Also write the dataset to a SQLite database with a normalised schema
+    #[arg(long, value_name = "PATH")]
+    pub sqlite: Option<String>,
+
+    /// Insert or update the votings in an existing database instead of recreating it
+    #[arg(long, requires = "sqlite")]
+    pub upsert: bool,
+
+    /// Also load the dataset into PostgreSQL, e.g. "host=localhost user=postgres dbname=votings".
+    /// The schema is created and migrated automatically and every scrape is recorded as a run.
+    #[arg(long, value_name = "CONNECTION_STRING")]
+    pub postgres: Option<String>,
+
+    /// Reuse the votings parsed by a previous run that did not complete, as found in its
+    /// checkpoint, instead of fetching them again
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Also extract the votings scheduled for the next ballot dates, without results and with
+    /// the status "scheduled"
+    #[arg(long, global = true)]
+    pub upcoming: bool,
+
+    /// Mapping of voting numbers to the identifiers of Swissvotes and of the Federal Statistical
+    /// Office, in addition to the bundled one and taking precedence over it
+    #[arg(long, value_name = "PATH")]
+    pub crosswalk: Option<String>,
+
+    /// Dataset of Swissvotes exported as CSV, whose "anr" are matched to the votings on their
+    /// date and title
+    #[arg(long, value_name = "PATH")]
+    pub swissvotes: Option<String>,
+
+    /// Directory with the JSON files of the Federal Statistical Office, whose identifiers are
+    /// matched to the votings on their date and title
+    #[arg(long, value_name = "DIRECTORY")]
+    pub fso: Option<String>,
+
+    /// Also write the results in long format, one row per voting and answer or measure, and one
+    /// row per voting, canton and answer when the files of --fso are given
+    #[arg(long)]
+    pub long: bool,
+
+    /// Also write a copy of the dataset, and of the results in long format, with the column names
+    /// and categorical values translated, e.g. data_de.csv
+    #[arg(long, value_enum)]
+    pub locale: Option<Locale>,
+
+    /// Scraping profile with the selectors, labels and URLs to use instead of the built-in one
+    #[arg(long, value_name = "PATH")]
+    pub profile: Option<String>,
+
+    /// Format of the log lines written to stderr, the level is set with RUST_LOG
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    pub log_format: LogFormat,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Load an existing dataset (CSV or SQLite) and report any problem with its schema
+    Check {
+        /// Path of the dataset, e.g. data.csv
+        path: String,
+    },
+    /// Compare two releases of the dataset, matching the votings on their number
+    Diff {
+        /// Path of the previous release
+        old: String,
+        /// Path of the new release
+        new: String,
+        /// Largest difference between two numbers that is not reported as a change
+        #[arg(long, default_value_t = 1e-6)]
+        tolerance: f64,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Poll the summary page and add new votings, or update recent votings whose results
+    /// changed, to the dataset in the working directory
+    Watch {
+        /// Seconds between two polls of the summary page
+        #[arg(long, default_value_t = 300)]
+        interval: u64,
+        /// Number of days after a voting during which its page is checked for changes
+        #[arg(long, default_value_t = 30)]
+        recheck_days: i64,
+        /// Command run through `sh -c` after the dataset changed. The numbers of the added and
+        /// updated votings are passed in VOTINGS_ADDED and VOTINGS_UPDATED.
+        #[arg(long, value_name = "COMMAND")]
+        on_change: Option<String>,
+        /// URL to which the numbers of the added and updated votings are posted as JSON
+        #[arg(long, value_name = "URL")]
+        webhook: Option<String>,
+    },
+    /// Extract a single voting and show how every cell of its detail page was used
+    Inspect {
+        /// Number of the voting or URL of its detail page
+        voting: String,
+        /// Also save a copy of the detail page with the labels and values highlighted
+        #[arg(long, value_name = "PATH")]
+        annotated_html: Option<String>,
+    },
+    /// Report the share of null values of every column, overall and per decade, and the
+    /// votings where a value that should be present is missing
+    Completeness {
+        /// Path of the dataset, e.g. data.csv
+        path: String,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Import the results per district and municipality from the JSON files of the Federal
+    /// Statistical Office in a directory, matched to the votings of the dataset on their date
+    /// and title
+    ImportFso {
+        /// Directory with the JSON files, one per ballot date
+        directory: String,
+        /// Path of the dataset whose voting numbers are used
+        #[arg(long, default_value = "data.csv")]
+        dataset: String,
+    },
+    /// Compare the votings of the dataset with an independent source, matched on their date and
+    /// title, and report every value that differs. Exits with an error if any does.
+    Reconcile {
+        /// Path of the dataset, e.g. data.csv
+        #[arg(long, default_value = "data.csv")]
+        dataset: String,
+        /// Directory with the JSON files of the Federal Statistical Office
+        #[arg(long, value_name = "DIRECTORY", required_unless_present = "swissvotes")]
+        fso: Option<String>,
+        /// Dataset of Swissvotes exported as CSV
+        #[arg(long, value_name = "PATH", conflicts_with = "fso")]
+        swissvotes: Option<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    De,
+    Fr,
+    It,
+    En,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::constants::{
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_YES, DATE_OF_VOTING,
+    DOMESTIC_VOTERS, INVALID_VOTING_BALLOTS, KIND, OUTCOME, OVERSEAS_VOTERS, PARTICIPATION,
+    RATIO_NO, RATIO_YES, RECOMMENDATION, RESULT_STATUS, SUPPLEMENTARY_URL, TITLE_DE, TITLE_FR,
+    TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALIDATED_ON, VALID_VOTING_BALLOTS,
+};
+use crate::converters::date_to_decade;
+use crate::data::{Data, Tristate, VotingStatus};
+
+#[derive(PartialEq)]
+enum Presence {
+    Present,
+    NotApplicable,
+    Missing,
+}
+
+// Columns without a value on most pages: their nulls are counted, but not listed per voting.
+// Pages older than a year carry no result status.
+const OPTIONAL_COLUMNS: [&str; 4] = [
+    RECOMMENDATION,
+    SUPPLEMENTARY_URL,
+    RESULT_STATUS,
+    VALIDATED_ON,
+];
+
+#[derive(Default)]
+pub struct Counts {
+    pub nulls: usize,
+    pub total: usize,
+}
+
+pub struct ColumnCompleteness {
+    pub column: &'static str,
+    pub overall: Counts,
+    // Votings without a date are grouped under `None`
+    pub per_decade: BTreeMap<Option<i32>, Counts>,
+    pub missing_in: Vec<Option<u32>>,
+}
+
+fn option<T>(value: &Option<T>) -> Presence {
+    match value {
+        Some(_) => Presence::Present,
+        None => Presence::Missing,
+    }
+}
+
+fn text(value: &str) -> Presence {
+    match value.trim().is_empty() {
+        true => Presence::Missing,
+        false => Presence::Present,
+    }
+}
+
+fn tristate<T>(value: &Tristate<T>) -> Presence {
+    match value {
+        Tristate::Value(_) => Presence::Present,
+        Tristate::NotApplicable => Presence::NotApplicable,
+        Tristate::Missing => Presence::Missing,
+    }
+}
+
+fn presences_of(data: &Data, idx: usize) -> Vec<(&'static str, Presence)> {
+    vec![
+        (DATE_OF_VOTING, option(&data.date_of_voting[idx])),
+        (TITLE_IT, text(&data.title_it[idx])),
+        (TITLE_FR, text(&data.title_fr[idx])),
+        (TITLE_DE, text(&data.title_de[idx])),
+        (KIND, text(&data.kind[idx])),
+        (RECOMMENDATION, option(&data.recommendation[idx])),
+        (TOTAL_VOTERS, option(&data.total_voters[idx])),
+        (DOMESTIC_VOTERS, option(&data.domestic_voters[idx])),
+        (OVERSEAS_VOTERS, tristate(&data.overseas_voters[idx])),
+        (BALLOTS_RETURNED, option(&data.ballots_returned[idx])),
+        (PARTICIPATION, option(&data.participation[idx])),
+        (
+            INVALID_VOTING_BALLOTS,
+            option(&data.invalid_voting_ballots[idx]),
+        ),
+        (
+            BLANK_VOTING_BALLOTS,
+            option(&data.blank_voting_ballots[idx]),
+        ),
+        (
+            VALID_VOTING_BALLOTS,
+            option(&data.valid_voting_ballots[idx]),
+        ),
+        (TOTAL_YES, option(&data.total_yes[idx])),
+        (RATIO_YES, option(&data.ratio_yes[idx])),
+        (TOTAL_NO, option(&data.total_no[idx])),
+        (RATIO_NO, option(&data.ratio_no[idx])),
+        (CANTONS_VOTING_YES, tristate(&data.cantons_voting_yes[idx])),
+        (CANTONS_VOTING_NO, tristate(&data.cantons_voting_no[idx])),
+        (OUTCOME, option(&data.outcome[idx])),
+        (SUPPLEMENTARY_URL, option(&data.supplementary_url[idx])),
+        (RESULT_STATUS, option(&data.result_status[idx])),
+        (VALIDATED_ON, option(&data.validated_on[idx])),
+    ]
+}
+
+pub fn completeness_of(data: &Data) -> Vec<ColumnCompleteness> {
+    let mut report: Vec<ColumnCompleteness> = Vec::new();
+    for idx in 0..data.no.len() {
+        let decade = data.date_of_voting[idx].as_ref().map(date_to_decade);
+        for (position, (column, presence)) in presences_of(data, idx).into_iter().enumerate() {
+            if report.len() == position {
+                report.push(ColumnCompleteness {
+                    column,
+                    overall: Counts::default(),
+                    per_decade: BTreeMap::new(),
+                    missing_in: Vec::new(),
+                });
+            }
+            let completeness = &mut report[position];
+
+            // Fields that did not exist in an era are null in the dataset, hence counted here,
+            // but they are not reported as missing
+            let is_null = presence != Presence::Present;
+            let counts = completeness.per_decade.entry(decade).or_default();
+            counts.total += 1;
+            counts.nulls += is_null as usize;
+            completeness.overall.total += 1;
+            completeness.overall.nulls += is_null as usize;
+
+            // Scheduled votings have no results yet, which are not missing either
+            if presence == Presence::Missing
+                && !OPTIONAL_COLUMNS.contains(&column)
+                && data.status[idx] == VotingStatus::Completed
+            {
+                completeness.missing_in.push(data.no[idx]);
+            }
+        }
+    }
+    report
+}
+
+fn share(counts: &Counts) -> f64 {
+    match counts.total {
+        0 => 0.0,
+        total => counts.nulls as f64 / total as f64,
+    }
+}
+
+fn decade_to_string(decade: &Option<i32>) -> String {
+    match decade {
+        Some(decade) => format!("{}s", decade),
+        None => "unknown".to_string(),
+    }
+}
+
+fn numbers_to_string(numbers: &[Option<u32>]) -> String {
+    numbers
+        .iter()
+        .map(|no| match no {
+            Some(no) => no.to_string(),
+            None => "?".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn completeness_to_text(report: &[ColumnCompleteness]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for completeness in report {
+        lines.push(format!(
+            "{}: {:.1}% null ({} of {})",
+            completeness.column,
+            share(&completeness.overall) * 100.0,
+            completeness.overall.nulls,
+            completeness.overall.total
+        ));
+        for (decade, counts) in &completeness.per_decade {
+            if counts.nulls > 0 {
+                lines.push(format!(
+                    "  {}: {:.1}% null ({} of {})",
+                    decade_to_string(decade),
+                    share(counts) * 100.0,
+                    counts.nulls,
+                    counts.total
+                ));
+            }
+        }
+        if !completeness.missing_in.is_empty() {
+            lines.push(format!(
+                "  missing in votings {}",
+                numbers_to_string(&completeness.missing_in)
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn completeness_to_json(report: &[ColumnCompleteness]) -> Value {
+    Value::Array(
+        report
+            .iter()
+            .map(|completeness| {
+                json!({
+                    "column": completeness.column,
+                    "nulls": completeness.overall.nulls,
+                    "total": completeness.overall.total,
+                    "share": share(&completeness.overall),
+                    "per_decade": completeness
+                        .per_decade
+                        .iter()
+                        .map(|(decade, counts)| json!({
+                            "decade": decade,
+                            "nulls": counts.nulls,
+                            "total": counts.total,
+                            "share": share(counts),
+                        }))
+                        .collect::<Vec<_>>(),
+                    "missing_in": completeness.missing_in,
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::data::Row;
+
+    fn completed(no: u32) -> Row {
+        Row {
+            no: Some(no),
+            date_of_voting: chrono::NaiveDate::from_ymd_opt(1970, 6, 7),
+            title_it: "Legge".to_string(),
+            title_fr: "Loi".to_string(),
+            title_de: "Gesetz".to_string(),
+            kind: "referendum".to_string(),
+            total_voters: Some(1_574_947),
+            ratio_yes: Some(Decimal::new(460, 3)),
+            overseas_voters: Tristate::NotApplicable,
+            status: VotingStatus::Completed,
+            ..Default::default()
+        }
+    }
+
+    fn column<'a>(report: &'a [ColumnCompleteness], column: &str) -> &'a ColumnCompleteness {
+        report
+            .iter()
+            .find(|completeness| completeness.column == column)
+            .unwrap()
+    }
+
+    #[test]
+    fn a_missing_required_column_is_listed_per_voting() {
+        let mut data = Data::default();
+        data.update(completed(212));
+        data.update(Row {
+            total_voters: None,
+            ..completed(213)
+        });
+        data.update(Row {
+            status: VotingStatus::Scheduled,
+            total_voters: None,
+            ..completed(214)
+        });
+        let report = completeness_of(&data);
+
+        let total_voters = column(&report, TOTAL_VOTERS);
+        assert_eq!(total_voters.overall.nulls, 2);
+        assert_eq!(total_voters.overall.total, 3);
+        assert_eq!(total_voters.per_decade[&Some(1970)].nulls, 2);
+        // A scheduled voting has no results yet
+        assert_eq!(total_voters.missing_in, vec![Some(213)]);
+    }
+
+    #[test]
+    fn optional_and_not_applicable_columns_are_only_counted() {
+        let mut data = Data::default();
+        data.update(completed(212));
+        let report = completeness_of(&data);
+
+        for name in [RECOMMENDATION, RESULT_STATUS, VALIDATED_ON, OVERSEAS_VOTERS] {
+            let completeness = column(&report, name);
+            assert_eq!(completeness.overall.nulls, 1, "nulls of {}", name);
+            assert!(completeness.missing_in.is_empty(), "missing in {}", name);
+        }
+        assert_eq!(
+            column(&report, CANTONS_VOTING_YES).missing_in,
+            vec![Some(212)]
+        );
+    }
+}
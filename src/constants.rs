@@ -1,26 +1,107 @@
-pub const URL_SUMMARY_PAGE_IT: &str =
-    "https://www.bk.admin.ch/ch/i/pore/va/vab_2_2_4_1_gesamt.html";
-pub const URL_SUMMARY_PAGE_DE: &str =
-    "https://www.bk.admin.ch/ch/d/pore/va/vab_2_2_4_1_gesamt.html";
-pub const URL_SUMMARY_PAGE_FR: &str =
-    "https://www.bk.admin.ch/ch/f/pore/va/vab_2_2_4_1_gesamt.html";
+pub const DATASET_FILE: &str = "data.csv";
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const DATAPACKAGE_FILE: &str = "datapackage.json";
+pub const KAGGLE_METADATA_FILE: &str = "dataset-metadata.json";
+pub const RUN_SUMMARY_FILE: &str = "run_summary.json";
+pub const CHECKPOINT_FILE: &str = "checkpoint.jsonl";
+pub const DISTRICT_RESULTS_FILE: &str = "district_results.csv";
+pub const MUNICIPALITY_RESULTS_FILE: &str = "municipality_results.csv";
+pub const CROSSWALK_FILE: &str = "crosswalk.csv";
+pub const ANSWERS_LONG_FILE: &str = "answers_long.csv";
+pub const MEASURES_LONG_FILE: &str = "measures_long.csv";
+pub const CANTON_ANSWERS_LONG_FILE: &str = "canton_answers_long.csv";
 
+pub const FETCH_ATTEMPTS: u32 = 3;
+pub const FETCH_RETRY_DELAY_MS: u64 = 500;
+pub const NUMBER_OF_SLOWEST_PAGES: usize = 10;
+// Votings of the last year are expected to show whether their results are provisional or final
+pub const RESULT_STATUS_EXPECTED_DAYS: i64 = 365;
+
+pub const DATASET_TITLE: &str = "Swiss Popular Voting";
+pub const KAGGLE_DATASET_ID: &str = "vascos/swiss-popular-voting";
+pub const LICENSE_NAME: &str = "terms_by";
+pub const LICENSE_TITLE: &str = "Open use. Must provide the source.";
+pub const LICENSE_PATH: &str = "https://opendata.swiss/en/terms-of-use";
+
+// Characters used as thousands separators on the Chancellery pages: ASCII and typographic
+// apostrophes, non-breaking space, thin space and narrow non-breaking space.
+pub const THOUSANDS_SEPARATORS: [char; 6] = ['\'', '’', 'ʼ', '\u{00A0}', '\u{2009}', '\u{202F}'];
+
+pub const NO: &str = "no";
 pub const TITLE_IT: &str = "title_it";
 pub const TITLE_FR: &str = "title_fr";
 pub const TITLE_DE: &str = "title_de";
 
+pub const KIND: &str = "kind";
+pub const RECOMMENDATION: &str = "recommendation";
 pub const DATE_OF_VOTING: &str = "date_of_voting";
 pub const OUTCOME: &str = "outcome";
+pub const STATUS: &str = "status";
+pub const SWISSVOTES_ANR: &str = "swissvotes_anr";
+pub const FSO_ID: &str = "fso_id";
+pub const RESULT_STATUS: &str = "result_status";
+pub const VALIDATED_ON: &str = "validated_on";
+pub const YEAR: &str = "year";
+pub const DECADE: &str = "decade";
+pub const QUARTER: &str = "quarter";
+pub const WEEKDAY: &str = "weekday";
+pub const LEGISLATURE_PERIOD: &str = "legislature_period";
+pub const ELECTORATE: &str = "electorate";
 pub const TOTAL_VOTERS: &str = "total_voters";
+pub const DOMESTIC_VOTERS: &str = "domestic_voters";
 pub const OVERSEAS_VOTERS: &str = "overseas_voters";
+pub const OVERSEAS_VOTERS_STATUS: &str = "overseas_voters_status";
 pub const BALLOTS_RETURNED: &str = "ballots_returned";
 pub const PARTICIPATION: &str = "participation";
+pub const PARTICIPATION_FRACTION: &str = "participation_fraction";
 pub const BLANK_VOTING_BALLOTS: &str = "blank_voting_ballots";
 pub const INVALID_VOTING_BALLOTS: &str = "invalid_voting_ballots";
 pub const VALID_VOTING_BALLOTS: &str = "valid_voting_ballots";
 pub const TOTAL_YES: &str = "total_yes";
 pub const RATIO_YES: &str = "ratio_yes";
+pub const RATIO_YES_FRACTION: &str = "ratio_yes_fraction";
 pub const TOTAL_NO: &str = "total_no";
 pub const RATIO_NO: &str = "ratio_no";
+pub const RATIO_NO_FRACTION: &str = "ratio_no_fraction";
 pub const CANTONS_VOTING_YES: &str = "cantons_voting_yes";
 pub const CANTONS_VOTING_NO: &str = "cantons_voting_no";
+pub const CANTONS_VOTING_STATUS: &str = "cantons_voting_status";
+pub const SOURCE_URL_IT: &str = "source_url_it";
+pub const SOURCE_URL_DE: &str = "source_url_de";
+pub const SOURCE_URL_FR: &str = "source_url_fr";
+pub const SUPPLEMENTARY_URL: &str = "supplementary_url";
+pub const FETCHED_AT: &str = "fetched_at";
+pub const CONTENT_SHA256: &str = "content_sha256";
+
+// Dates (year, month, day) at which the rules of federal votings changed
+pub const WOMEN_SUFFRAGE_SINCE: (i32, u32, u32) = (1971, 3, 16);
+pub const OVERSEAS_VOTERS_SINCE: (i32, u32, u32) = (1977, 7, 1);
+pub const JURA_SINCE: (i32, u32, u32) = (1979, 1, 1);
+
+// Cantonal votes before and after the creation of the canton of Jura
+pub const CANTONS_BEFORE_JURA: u32 = 22;
+pub const CANTONS_SINCE_JURA: u32 = 23;
+
+// Years in which the National Council was elected, i.e. the start of each legislature period.
+pub const NATIONAL_COUNCIL_ELECTION_YEARS: [i32; 52] = [
+    1848, 1851, 1854, 1857, 1860, 1863, 1866, 1869, 1872, 1875, 1878, 1881, 1884, 1887, 1890, 1893,
+    1896, 1899, 1902, 1905, 1908, 1911, 1914, 1917, 1919, 1922, 1925, 1928, 1931, 1935, 1939, 1943,
+    1947, 1951, 1955, 1959, 1963, 1967, 1971, 1975, 1979, 1983, 1987, 1991, 1995, 1999, 2003, 2007,
+    2011, 2015, 2019, 2023,
+];
+
+// Columns of the results per district and municipality
+pub const CANTON_NUMBER: &str = "canton_number";
+pub const CANTON: &str = "canton";
+pub const DISTRICT_NUMBER: &str = "district_number";
+pub const DISTRICT: &str = "district";
+pub const MUNICIPALITY_NUMBER: &str = "municipality_number";
+pub const MUNICIPALITY: &str = "municipality";
+pub const COUNTED: &str = "counted";
+
+// Columns of the results in long format
+pub const ANSWER: &str = "answer";
+pub const COUNT: &str = "count";
+pub const SHARE: &str = "share";
+pub const MEASURE: &str = "measure";
+pub const VALUE: &str = "value";
@@ -1,96 +1,262 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use rust_decimal::Decimal;
+
+use crate::constants::{NATIONAL_COUNCIL_ELECTION_YEARS, THOUSANDS_SEPARATORS};
+
 pub fn string_to_u32(candidate: Option<&String>) -> Option<u32> {
-    match candidate.is_none() {
-        true => None,
-        false => {
-            let candidate_clean = candidate.unwrap().replace("'", "").parse::<u32>();
-            match candidate_clean {
-                Ok(value) => Some(value),
-                Err(_) => None,
-            }
-        }
-    }
+    normalise_number(candidate?)?.parse::<u32>().ok()
 }
 
-pub fn string_to_f32(candidate: String) -> Option<f32> {
-    let cleaned_str = candidate.replace("'", "").parse::<f32>();
-    match cleaned_str {
-        Ok(value) => Some(value),
-        Err(_) => None,
-    }
+pub fn string_to_decimal(candidate: &str) -> Option<Decimal> {
+    Decimal::from_str(&normalise_number(candidate)?).ok()
 }
 
-pub fn ratio_to_f32(candidate: Option<&String>) -> Option<f32> {
-    match candidate.is_none() {
-        true => None,
-        false => {
-            let cleaned_str = candidate.unwrap().trim_end_matches('%');
-            // Parse the cleaned string into a f32
-            match cleaned_str.parse::<f32>() {
-                Ok(value) => {
-                    let decimal = value / 100.0; // Convert percentage to decimal
-                    Some(decimal)
-                }
-                Err(_) => None,
-            }
-        }
-    }
+pub fn ratio_to_decimal(candidate: Option<&String>) -> Option<Decimal> {
+    let cleaned_str = strip_footnote_marker(candidate?).trim_end_matches('%');
+    let mut value = string_to_decimal(cleaned_str)?;
+
+    // Convert percentage to fraction by shifting the decimal point, so that the number of
+    // published decimals is kept, e.g. "50.0%" becomes 0.500
+    value.set_scale(value.scale() + 2).ok()?;
+    Some(value)
 }
 
-pub fn integer_and_fraction_to_f32(candidate: Option<&String>) -> Option<f32> {
-    if candidate.is_none() {
-        return None;
-    }
+pub fn integer_and_fraction_to_decimal(candidate: Option<&String>) -> Option<Decimal> {
     // Split the input into integer and fraction parts
-    let parts: Vec<&str> = candidate.unwrap().split_whitespace().collect();
+    let parts: Vec<&str> = strip_footnote_marker(candidate?)
+        .split_whitespace()
+        .collect();
 
     match parts.len() {
         0 => None,
         1 => {
             let value = parts[0];
             if value.contains('/') {
-                return string_to_f32(value.to_string())
+                return fraction_to_decimal(value);
             }
-            return string_to_f32(value.to_string())
+            string_to_decimal(value)
         }
         2 => {
             // Parse the integer part
-            let integer_part: f32 = parts[0].parse().ok()?;
+            let integer_part = string_to_decimal(parts[0])?;
 
             // Parse the fraction part (e.g., "1/2")
-            let fraction_parts: Vec<&str> = parts[1].split('/').collect();
+            let fraction = fraction_to_decimal(parts[1])?;
+
+            // Return the combined value
+            Some(integer_part + fraction)
+        }
+        _ => None,
+    }
+}
+
+fn fraction_to_decimal(candidate: &str) -> Option<Decimal> {
+    let fraction_parts: Vec<&str> = candidate.split('/').collect();
+
+    // Return None if the fraction part is not in the form "numerator/denominator"
+    if fraction_parts.len() != 2 {
+        return None;
+    }
+
+    // Parse numerator and denominator, written in the same formats as any other figure
+    let numerator = string_to_decimal(fraction_parts[0])?;
+    let denominator = string_to_decimal(fraction_parts[1])?;
+
+    // Calculate the fractional value, without trailing zeros (half-cantons count as 1/2)
+    Some(numerator.checked_div(denominator)?.normalize())
+}
 
-            // Return None if the fraction part is not in the form "numerator/denominator"
-            if fraction_parts.len() != 2 {
-                return None;
+pub fn fraction_to_published_percentage(fraction: &Decimal) -> Decimal {
+    // Inverse of `ratio_to_decimal`: 0.523 is displayed as 52.3
+    let mut percentage = *fraction * Decimal::ONE_HUNDRED;
+    percentage.rescale(fraction.scale().saturating_sub(2));
+    percentage
+}
+
+pub fn normalise_number(candidate: &str) -> Option<String> {
+    // Figures on the Chancellery pages are written as e.g. "1'234'567", "1’234’567" or
+    // "1 234 567" (with non-breaking or thin spaces), and may be followed by a footnote marker.
+    let mut number: String = strip_footnote_marker(candidate)
+        .chars()
+        .filter(|c| !THOUSANDS_SEPARATORS.contains(c) && !c.is_whitespace())
+        .collect();
+
+    // A lone comma is a decimal separator, e.g. "52,3"
+    if number.contains(',') && !number.contains('.') {
+        number = number.replace(',', ".");
+    }
+
+    match number.is_empty() {
+        true => None,
+        false => Some(number),
+    }
+}
+
+fn strip_footnote_marker(candidate: &str) -> &str {
+    // Asterisks and superscript digits can never be part of a figure
+    let candidate = candidate
+        .trim()
+        .trim_end_matches(|c: char| c == '*' || "¹²³⁴⁵⁶⁷⁸⁹⁰".contains(c))
+        .trim_end();
+
+    let Some(without_parenthesis) = candidate.strip_suffix(')') else {
+        return candidate;
+    };
+    let marker_start = without_parenthesis.trim_end_matches(|c: char| c.is_ascii_digit());
+    if marker_start.ends_with(|c: char| c.is_whitespace() || c == '(') {
+        // Marker separated from the figure, e.g. "1'234 1)" or "1'234 (1)"
+        return marker_start.trim_end_matches('(').trim_end();
+    }
+
+    // Marker glued to the figure, e.g. "1'234'5671)": the last group of a figure with
+    // thousands separators has exactly three digits, everything after it is the marker.
+    match marker_start.rfind(|c: char| THOUSANDS_SEPARATORS.contains(&c)) {
+        Some(idx) => {
+            let separator_length = marker_start[idx..].chars().next().unwrap().len_utf8();
+            let last_group_start = idx + separator_length;
+            match without_parenthesis.len() > last_group_start + 3 {
+                true => &without_parenthesis[..last_group_start + 3],
+                false => candidate,
             }
+        }
+        None => candidate,
+    }
+}
+
+pub fn convert_date_to_iso_format(date: &str) -> Option<NaiveDate> {
+    // Dates on the Chancellery pages are written as "DD.MM.YYYY", with or without zero-padding.
+    // Parsing them through chrono rejects impossible dates such as "31.02.2000".
+    NaiveDate::parse_from_str(date.trim(), "%d.%m.%Y").ok()
+}
 
-            // Parse numerator and denominator
-            let numerator: f32 = fraction_parts[0].parse().ok()?;
-            let denominator: f32 = fraction_parts[1].parse().ok()?;
+pub fn date_to_decade(date: &NaiveDate) -> i32 {
+    date.year() - date.year().rem_euclid(10)
+}
 
-            // Calculate the fractional value
-            let fraction = numerator / denominator;
+pub fn date_to_quarter(date: &NaiveDate) -> u32 {
+    (date.month() - 1) / 3 + 1
+}
 
-            // Return the combined value as f32
-            Some(integer_part + fraction)
+pub fn date_to_weekday(date: &NaiveDate) -> String {
+    match date.weekday() {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+    .to_string()
+}
+
+pub fn date_to_legislature_period(date: &NaiveDate) -> Option<u32> {
+    // A legislature period of the Federal Assembly starts with the constituent session that
+    // follows the elections of the National Council, which takes place at the beginning of
+    // December of the election year (November for the first one in 1848).
+    let mut number_of_legislature: Option<u32> = None;
+    for (idx, year) in NATIONAL_COUNCIL_ELECTION_YEARS.iter().enumerate() {
+        let month = if *year == 1848 { 11 } else { 12 };
+        let start_of_legislature = NaiveDate::from_ymd_opt(*year, month, 1)?;
+        if *date < start_of_legislature {
+            break;
         }
-        _ => None,
+        number_of_legislature = Some(idx as u32 + 1);
     }
+    number_of_legislature
 }
 
-pub fn convert_date_to_us_format(date: &str) -> Option<String> {
-    // Split the date by `.`
-    let parts: Vec<&str> = date.split('.').collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Ensure there are exactly 3 parts: day, month, year
-    if parts.len() == 3 {
-        let day = parts[0];
-        let month = parts[1];
-        let year = parts[2];
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn normalise_number_removes_thousands_separators() {
+        for candidate in [
+            "1'234",
+            "1’234",
+            "1 234",
+            "1\u{00A0}234",
+            "1\u{2009}234",
+            "1\u{202F}234",
+        ] {
+            assert_eq!(
+                normalise_number(candidate).as_deref(),
+                Some("1234"),
+                "{}",
+                candidate
+            );
+        }
+        assert_eq!(normalise_number("1'234'567").as_deref(), Some("1234567"));
+        assert_eq!(normalise_number(""), None);
+    }
 
-        // Return the formatted date as "YYYY-MM-DD"
-        return Some(format!("{}-{}-{}", year, month, day));
+    #[test]
+    fn normalise_number_reads_a_lone_comma_as_decimal_separator() {
+        assert_eq!(normalise_number("12,5").as_deref(), Some("12.5"));
+        assert_eq!(normalise_number("12.5").as_deref(), Some("12.5"));
+        // With a point, the comma cannot be the decimal separator and is left for the parser
+        assert_eq!(normalise_number("1,234.5").as_deref(), Some("1,234.5"));
     }
 
-    None // Return None if the format is invalid
+    #[test]
+    fn string_to_u32_parses_swiss_formats() {
+        assert_eq!(string_to_u32(Some(&"1'234".to_string())), Some(1234));
+        assert_eq!(string_to_u32(Some(&"1 234".to_string())), Some(1234));
+        assert_eq!(string_to_u32(Some(&"1'234 1)".to_string())), Some(1234));
+        assert_eq!(string_to_u32(Some(&"12,5".to_string())), None);
+    }
+
+    #[test]
+    fn ratio_to_decimal_keeps_the_published_decimals() {
+        assert_eq!(
+            ratio_to_decimal(Some(&"12.5%".to_string())),
+            Some(decimal("0.125"))
+        );
+        assert_eq!(
+            ratio_to_decimal(Some(&"12,5%".to_string())),
+            Some(decimal("0.125"))
+        );
+        assert_eq!(
+            ratio_to_decimal(Some(&"50.0%".to_string())).map(|value| value.scale()),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn integer_and_fraction_to_decimal_parses_half_cantons() {
+        assert_eq!(
+            integer_and_fraction_to_decimal(Some(&"20 1/2".to_string())),
+            Some(decimal("20.5"))
+        );
+        assert_eq!(
+            integer_and_fraction_to_decimal(Some(&"20\u{00A0}1/2".to_string())),
+            Some(decimal("20.5"))
+        );
+        assert_eq!(
+            integer_and_fraction_to_decimal(Some(&"5/2".to_string())),
+            Some(decimal("2.5"))
+        );
+        assert_eq!(
+            integer_and_fraction_to_decimal(Some(&"23".to_string())),
+            Some(decimal("23"))
+        );
+        assert_eq!(
+            integer_and_fraction_to_decimal(Some(&"1/0".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn fraction_to_decimal_accepts_thousands_separators() {
+        assert_eq!(fraction_to_decimal("1'000/2'000"), Some(decimal("0.5")));
+        assert_eq!(fraction_to_decimal("1\u{2009}000/4"), Some(decimal("250")));
+        assert_eq!(fraction_to_decimal("1/2/3"), None);
+    }
 }
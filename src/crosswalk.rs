@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fs;
+
+use log::{info, warn};
+use polars::df;
+use polars::frame::DataFrame;
+use polars::prelude::*;
+
+use crate::constants::{CROSSWALK_FILE, DATE_OF_VOTING, FSO_ID, NO, SWISSVOTES_ANR, TITLE_IT};
+use crate::data::{save_dataframe, Data};
+use crate::fso::fso_reference_votings;
+use crate::matching::match_voting;
+use crate::reconcile::ReferenceVoting;
+use crate::swissvotes::swissvotes_reference_votings;
+
+const BUNDLED_MAPPING: &str = include_str!("../mappings/crosswalk.csv");
+
+// Identifiers of the mapping file are trusted over the ones found by date and title
+#[derive(Clone, Copy, PartialEq)]
+enum Origin {
+    Mapping,
+    Match,
+}
+
+impl Origin {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Origin::Mapping => "mapping",
+            Origin::Match => "date and title",
+        }
+    }
+}
+
+struct Identifier {
+    value: String,
+    origin: Origin,
+}
+
+// Identifiers of the votings in other sources, by number of the voting
+#[derive(Default)]
+pub struct Crosswalk {
+    swissvotes_anr: HashMap<u32, Identifier>,
+    fso_id: HashMap<u32, Identifier>,
+}
+
+fn read_mapping(content: &str, name: &str, crosswalk: &mut Crosswalk) {
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_reader(content.as_bytes());
+    for (idx, record) in reader.records().enumerate() {
+        let Some((no, swissvotes_anr, fso_id)) = record.ok().and_then(|record| {
+            let cell = |position: usize| {
+                record
+                    .get(position)
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+            Some((cell(0)?.parse::<u32>().ok()?, cell(1), cell(2)))
+        }) else {
+            warn!(
+                "Ignored invalid entry {} of the crosswalk {}",
+                idx + 1,
+                name
+            );
+            continue;
+        };
+
+        for (identifiers, value) in [
+            (&mut crosswalk.swissvotes_anr, swissvotes_anr),
+            (&mut crosswalk.fso_id, fso_id),
+        ] {
+            if let Some(value) = value {
+                identifiers.insert(
+                    no,
+                    Identifier {
+                        value,
+                        origin: Origin::Mapping,
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn match_identifiers(
+    data: &Data,
+    references: Vec<ReferenceVoting>,
+    identifiers: &mut HashMap<u32, Identifier>,
+) {
+    let mut matched = 0;
+    for reference in references {
+        let Some(id) = reference.id else {
+            continue;
+        };
+        let Some(no) =
+            match_voting(data, reference.date, &reference.titles).and_then(|idx| data.no[idx])
+        else {
+            continue;
+        };
+        match identifiers.get(&no) {
+            None => {
+                identifiers.insert(
+                    no,
+                    Identifier {
+                        value: id,
+                        origin: Origin::Match,
+                    },
+                );
+                matched += 1;
+            }
+            Some(identifier) if identifier.origin == Origin::Match && identifier.value != id => {
+                warn!(
+                    "Voting {} matches both {} and {} of {}, add it to the crosswalk",
+                    no, identifier.value, id, reference.source
+                );
+            }
+            Some(_) => (),
+        }
+    }
+    info!("{} identifiers found by date and title", matched);
+}
+
+// Identifiers given to more than one voting, with the numbers of these votings
+fn reverse_collisions(identifiers: &HashMap<u32, Identifier>) -> Vec<(String, Vec<u32>)> {
+    let mut votings: HashMap<&str, Vec<u32>> = HashMap::new();
+    for (no, identifier) in identifiers {
+        votings.entry(&identifier.value).or_default().push(*no);
+    }
+    let mut collisions: Vec<(String, Vec<u32>)> = votings
+        .into_iter()
+        .filter(|(_, numbers)| numbers.len() > 1)
+        .map(|(value, mut numbers)| {
+            numbers.sort();
+            (value.to_string(), numbers)
+        })
+        .collect();
+    collisions.sort();
+    collisions
+}
+
+pub fn build_crosswalk(
+    data: &Data,
+    mapping: Option<&str>,
+    swissvotes: Option<&str>,
+    fso: Option<&str>,
+) -> Crosswalk {
+    let mut crosswalk = Crosswalk::default();
+    read_mapping(BUNDLED_MAPPING, "bundled", &mut crosswalk);
+    if let Some(path) = mapping {
+        let content = fs::read_to_string(path).expect("Could not read the crosswalk");
+        read_mapping(&content, path, &mut crosswalk);
+    }
+
+    for no in crosswalk
+        .swissvotes_anr
+        .keys()
+        .chain(crosswalk.fso_id.keys())
+    {
+        if !data.no.contains(&Some(*no)) {
+            warn!("Voting {} of the crosswalk is not in the dataset", no);
+        }
+    }
+
+    // Sources are checked before the extraction, one that became unreadable since is left out
+    for (references, identifiers) in [
+        (
+            swissvotes.map(swissvotes_reference_votings),
+            &mut crosswalk.swissvotes_anr,
+        ),
+        (fso.map(fso_reference_votings), &mut crosswalk.fso_id),
+    ] {
+        match references {
+            Some(Ok(references)) => match_identifiers(data, references, identifiers),
+            Some(Err(error)) => warn!("Crosswalk built without a source: {}", error),
+            None => {}
+        }
+    }
+
+    // Each identifier belongs to one voting, e.g. an initiative and its counter-proposal of the
+    // same day may both match the same entry of the other source
+    for (column, identifiers) in [
+        (SWISSVOTES_ANR, &crosswalk.swissvotes_anr),
+        (FSO_ID, &crosswalk.fso_id),
+    ] {
+        for (value, numbers) in reverse_collisions(identifiers) {
+            warn!(
+                "{} {} is given to votings {}, add them to the crosswalk",
+                column,
+                value,
+                numbers
+                    .iter()
+                    .map(|no| no.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+    crosswalk
+}
+
+fn values_of(data: &Data, identifiers: &HashMap<u32, Identifier>) -> Vec<Option<String>> {
+    data.no
+        .iter()
+        .map(|no| {
+            no.and_then(|no| identifiers.get(&no))
+                .map(|identifier| identifier.value.clone())
+        })
+        .collect()
+}
+
+fn origins_of(data: &Data, identifiers: &HashMap<u32, Identifier>) -> Vec<Option<&'static str>> {
+    data.no
+        .iter()
+        .map(|no| {
+            no.and_then(|no| identifiers.get(&no))
+                .map(|identifier| identifier.origin.as_str())
+        })
+        .collect()
+}
+
+pub fn add_crosswalk_columns(df: &mut DataFrame, data: &Data, crosswalk: &Crosswalk) {
+    for (column, identifiers) in [
+        (SWISSVOTES_ANR, &crosswalk.swissvotes_anr),
+        (FSO_ID, &crosswalk.fso_id),
+    ] {
+        df.with_column(Series::new(column.into(), values_of(data, identifiers)))
+            .unwrap();
+    }
+}
+
+pub fn save_crosswalk(data: &Data, crosswalk: &Crosswalk) {
+    let dates: Vec<Option<String>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.map(|date| date.format("%Y-%m-%d").to_string()))
+        .collect();
+    let mut df: DataFrame = df!(
+        NO => data.no.clone(),
+        DATE_OF_VOTING => dates,
+        TITLE_IT => data.title_it.clone(),
+        SWISSVOTES_ANR => values_of(data, &crosswalk.swissvotes_anr),
+        format!("{}_origin", SWISSVOTES_ANR) => origins_of(data, &crosswalk.swissvotes_anr),
+        FSO_ID => values_of(data, &crosswalk.fso_id),
+        format!("{}_origin", FSO_ID) => origins_of(data, &crosswalk.fso_id),
+    )
+    .unwrap();
+    save_dataframe(&mut df, CROSSWALK_FILE);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::data::Row;
+
+    fn reference(id: &str, title_de: &str) -> ReferenceVoting {
+        ReferenceVoting {
+            source: "swissvotes.csv".to_string(),
+            id: Some(id.to_string()),
+            date: NaiveDate::from_ymd_opt(2010, 11, 28).unwrap(),
+            titles: vec![("de".to_string(), title_de.to_string())],
+            total_voters: None,
+            ballots_returned: None,
+            total_yes: None,
+            total_no: None,
+            cantons_voting_yes: None,
+            cantons_voting_no: None,
+            accepted: None,
+        }
+    }
+
+    fn votings_of_the_day() -> Data {
+        let mut data = Data::default();
+        for (no, title_de) in [
+            (
+                547,
+                "Volksinitiative «für die Ausschaffung krimineller Ausländer \
+                (Ausschaffungsinitiative)»",
+            ),
+            (
+                548,
+                "Bundesbeschluss über die Aus- und Wegweisung krimineller Ausländerinnen und \
+                Ausländer im Rahmen der Bundesverfassung (Gegenentwurf)",
+            ),
+        ] {
+            data.update(Row {
+                no: Some(no),
+                date_of_voting: NaiveDate::from_ymd_opt(2010, 11, 28),
+                title_de: title_de.to_string(),
+                ..Default::default()
+            });
+        }
+        data
+    }
+
+    #[test]
+    fn mapping_entries_are_read_with_empty_cells() {
+        let mut crosswalk = Crosswalk::default();
+        read_mapping(
+            "# comment\nno,swissvotes_anr,fso_id\n547,552,5520\n548,,5530\nx,1,2\n",
+            "test",
+            &mut crosswalk,
+        );
+        assert_eq!(crosswalk.swissvotes_anr.len(), 1);
+        assert_eq!(crosswalk.swissvotes_anr[&547].value, "552");
+        assert_eq!(crosswalk.fso_id[&548].value, "5530");
+        assert!(crosswalk.fso_id[&548].origin == Origin::Mapping);
+    }
+
+    #[test]
+    fn identifiers_are_matched_on_date_and_title() {
+        let mut identifiers = HashMap::new();
+        match_identifiers(
+            &votings_of_the_day(),
+            vec![
+                reference("552", "Ausschaffungsinitiative"),
+                reference("553", "Gegenentwurf zur Ausschaffungsinitiative"),
+            ],
+            &mut identifiers,
+        );
+        assert_eq!(identifiers[&547].value, "552");
+        assert!(identifiers[&547].origin == Origin::Match);
+        // The counter-proposal shares too few words with the title of the dataset
+        assert!(!identifiers.contains_key(&548));
+    }
+
+    #[test]
+    fn identifiers_of_several_votings_are_collisions() {
+        let mut identifiers = HashMap::new();
+        for (no, value, origin) in [
+            (547, "552", Origin::Mapping),
+            (548, "552", Origin::Match),
+            (549, "553", Origin::Match),
+        ] {
+            identifiers.insert(
+                no,
+                Identifier {
+                    value: value.to_string(),
+                    origin,
+                },
+            );
+        }
+        assert_eq!(
+            reverse_collisions(&identifiers),
+            vec![("552".to_string(), vec![547, 548])]
+        );
+    }
+}
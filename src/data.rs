@@ -1,17 +1,192 @@
 use crate::constants::{
-    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_YES, DATE_OF_VOTING,
-    INVALID_VOTING_BALLOTS, OUTCOME, OVERSEAS_VOTERS, PARTICIPATION, RATIO_NO, RATIO_YES, TITLE_DE,
-    TITLE_FR, TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALID_VOTING_BALLOTS,
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_STATUS,
+    CANTONS_VOTING_YES, CONTENT_SHA256, DATASET_FILE, DATE_OF_VOTING, DECADE, DOMESTIC_VOTERS,
+    ELECTORATE, FETCHED_AT, INVALID_VOTING_BALLOTS, KIND, LEGISLATURE_PERIOD, NO, OUTCOME,
+    OVERSEAS_VOTERS, OVERSEAS_VOTERS_STATUS, PARTICIPATION, PARTICIPATION_FRACTION, QUARTER,
+    RATIO_NO, RATIO_NO_FRACTION, RATIO_YES, RATIO_YES_FRACTION, RECOMMENDATION, RESULT_STATUS,
+    SOURCE_URL_DE, SOURCE_URL_FR, SOURCE_URL_IT, STATUS, SUPPLEMENTARY_URL, TITLE_DE, TITLE_FR,
+    TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALIDATED_ON, VALID_VOTING_BALLOTS, WEEKDAY, YEAR,
 };
+use crate::converters::{
+    date_to_decade, date_to_legislature_period, date_to_quarter, date_to_weekday,
+    fraction_to_published_percentage,
+};
+use crate::eras::date_to_electorate;
+use crate::matching::match_voting;
+use chrono::{DateTime, Datelike, NaiveDate, SecondsFormat, Utc};
 use polars::df;
 use polars::frame::DataFrame;
-use std::fs::File;
 use polars::prelude::*;
+use rust_decimal::Decimal;
+use std::fmt;
+use std::fs::File;
+use std::str::FromStr;
+
+// Value of a field that did not exist in every era, e.g. the Swiss abroad could not vote
+// before 1977. `NotApplicable` is a field that cannot have a value, `Missing` one that should
+// have a value but was not found.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Tristate<T> {
+    Value(T),
+    NotApplicable,
+    #[default]
+    Missing,
+}
+
+impl<T: Copy> Tristate<T> {
+    pub fn value(&self) -> Option<T> {
+        match self {
+            Tristate::Value(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self {
+            Tristate::Value(_) => "value",
+            Tristate::NotApplicable => "not_applicable",
+            Tristate::Missing => "missing",
+        }
+    }
+
+    pub fn from_status(value: Option<T>, status: Option<&str>) -> Tristate<T> {
+        match (value, status) {
+            (Some(value), _) => Tristate::Value(value),
+            (None, Some("not_applicable")) => Tristate::NotApplicable,
+            (None, _) => Tristate::Missing,
+        }
+    }
+}
+
+// Outcome of a voting. A voting can be rejected even with a majority of the people, or of
+// the cantons, and when an initiative and its counter-proposal are both accepted, the answers to
+// the tie-break question decide which one comes into force.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Accepted,
+    Rejected,
+    RejectedByTheCantons,
+    RejectedByThePeople,
+    CounterProposalPreferred,
+    InitiativePreferred,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 6] = [
+        Outcome::Accepted,
+        Outcome::Rejected,
+        Outcome::RejectedByTheCantons,
+        Outcome::RejectedByThePeople,
+        Outcome::CounterProposalPreferred,
+        Outcome::InitiativePreferred,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Accepted => "accepted",
+            Outcome::Rejected => "not accepted",
+            Outcome::RejectedByTheCantons => "not accepted by the cantons",
+            Outcome::RejectedByThePeople => "not accepted by the people",
+            Outcome::CounterProposalPreferred => "counter-proposal preferred",
+            Outcome::InitiativePreferred => "initiative preferred",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Outcome, ()> {
+        Outcome::ALL
+            .into_iter()
+            .find(|outcome| outcome.as_str() == value)
+            .ok_or(())
+    }
+}
+
+// Whether the results of a voting are published. Objects scheduled for a future ballot date
+// have their titles, kind and date, but no results yet.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum VotingStatus {
+    Scheduled,
+    #[default]
+    Completed,
+}
+
+impl VotingStatus {
+    pub const ALL: [VotingStatus; 2] = [VotingStatus::Scheduled, VotingStatus::Completed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VotingStatus::Scheduled => "scheduled",
+            VotingStatus::Completed => "completed",
+        }
+    }
+}
+
+impl fmt::Display for VotingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for VotingStatus {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<VotingStatus, ()> {
+        VotingStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or(())
+    }
+}
+
+// Results published on voting day are provisional until the Federal Council validates them
+// (Erwahrung), after which they are final.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResultStatus {
+    Provisional,
+    Final,
+}
+
+impl ResultStatus {
+    pub const ALL: [ResultStatus; 2] = [ResultStatus::Provisional, ResultStatus::Final];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultStatus::Provisional => "provisional",
+            ResultStatus::Final => "final",
+        }
+    }
+}
 
-#[derive(Default, Debug)]
+impl fmt::Display for ResultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ResultStatus {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<ResultStatus, ()> {
+        ResultStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or(())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct Row {
     pub no: Option<u32>,
-    pub date_of_voting: String,
+    pub date_of_voting: Option<NaiveDate>,
     pub title_it: String,
     pub title_fr: String,
     pub title_de: String,
@@ -19,25 +194,34 @@ pub struct Row {
     pub recommendation: Option<String>,
     pub total_voters: Option<u32>,
     pub domestic_voters: Option<u32>,
-    pub overseas_voters: Option<u32>,
+    pub overseas_voters: Tristate<u32>,
     pub ballots_returned: Option<u32>,
-    pub participation: Option<f32>,
+    pub participation: Option<Decimal>,
     pub invalid_voting_ballots: Option<u32>,
     pub blank_voting_ballots: Option<u32>,
     pub valid_voting_ballots: Option<u32>,
     pub total_yes: Option<u32>,
-    pub ratio_yes: Option<f32>,
+    pub ratio_yes: Option<Decimal>,
     pub total_no: Option<u32>,
-    pub ratio_no: Option<f32>,
-    pub cantons_voting_yes: Option<f32>,
-    pub cantons_voting_no: Option<f32>,
-    pub outcome: Option<String>,
+    pub ratio_no: Option<Decimal>,
+    pub cantons_voting_yes: Tristate<Decimal>,
+    pub cantons_voting_no: Tristate<Decimal>,
+    pub outcome: Option<Outcome>,
+    pub status: VotingStatus,
+    pub result_status: Option<ResultStatus>,
+    pub validated_on: Option<NaiveDate>,
+    pub source_url_it: String,
+    pub source_url_de: String,
+    pub source_url_fr: String,
+    pub supplementary_url: Option<String>,
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub content_sha256: String,
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct Data {
     pub no: Vec<Option<u32>>,
-    pub date_of_voting: Vec<String>,
+    pub date_of_voting: Vec<Option<NaiveDate>>,
     pub title_it: Vec<String>,
     pub title_fr: Vec<String>,
     pub title_de: Vec<String>,
@@ -45,19 +229,28 @@ pub struct Data {
     pub recommendation: Vec<Option<String>>,
     pub total_voters: Vec<Option<u32>>,
     pub domestic_voters: Vec<Option<u32>>,
-    pub overseas_voters: Vec<Option<u32>>,
+    pub overseas_voters: Vec<Tristate<u32>>,
     pub ballots_returned: Vec<Option<u32>>,
-    pub participation: Vec<Option<f32>>,
+    pub participation: Vec<Option<Decimal>>,
     pub invalid_voting_ballots: Vec<Option<u32>>,
     pub blank_voting_ballots: Vec<Option<u32>>,
     pub valid_voting_ballots: Vec<Option<u32>>,
     pub total_yes: Vec<Option<u32>>,
-    pub ratio_yes: Vec<Option<f32>>,
+    pub ratio_yes: Vec<Option<Decimal>>,
     pub total_no: Vec<Option<u32>>,
-    pub ratio_no: Vec<Option<f32>>,
-    pub cantons_voting_yes: Vec<Option<f32>>,
-    pub cantons_voting_no: Vec<Option<f32>>,
-    pub outcome: Vec<Option<String>>,
+    pub ratio_no: Vec<Option<Decimal>>,
+    pub cantons_voting_yes: Vec<Tristate<Decimal>>,
+    pub cantons_voting_no: Vec<Tristate<Decimal>>,
+    pub outcome: Vec<Option<Outcome>>,
+    pub status: Vec<VotingStatus>,
+    pub result_status: Vec<Option<ResultStatus>>,
+    pub validated_on: Vec<Option<NaiveDate>>,
+    pub source_url_it: Vec<String>,
+    pub source_url_de: Vec<String>,
+    pub source_url_fr: Vec<String>,
+    pub supplementary_url: Vec<Option<String>>,
+    pub fetched_at: Vec<Option<DateTime<Utc>>>,
+    pub content_sha256: Vec<String>,
 }
 
 impl Data {
@@ -84,42 +277,278 @@ impl Data {
         self.cantons_voting_yes.push(row.cantons_voting_yes);
         self.cantons_voting_no.push(row.cantons_voting_no);
         self.outcome.push(row.outcome);
+        self.status.push(row.status);
+        self.result_status.push(row.result_status);
+        self.validated_on.push(row.validated_on);
+        self.source_url_it.push(row.source_url_it);
+        self.source_url_de.push(row.source_url_de);
+        self.source_url_fr.push(row.source_url_fr);
+        self.supplementary_url.push(row.supplementary_url);
+        self.fetched_at.push(row.fetched_at);
+        self.content_sha256.push(row.content_sha256);
+    }
+
+    pub fn row(&self, idx: usize) -> Row {
+        Row {
+            no: self.no[idx],
+            date_of_voting: self.date_of_voting[idx],
+            title_it: self.title_it[idx].clone(),
+            title_fr: self.title_fr[idx].clone(),
+            title_de: self.title_de[idx].clone(),
+            kind: self.kind[idx].clone(),
+            recommendation: self.recommendation[idx].clone(),
+            total_voters: self.total_voters[idx],
+            domestic_voters: self.domestic_voters[idx],
+            overseas_voters: self.overseas_voters[idx],
+            ballots_returned: self.ballots_returned[idx],
+            participation: self.participation[idx],
+            invalid_voting_ballots: self.invalid_voting_ballots[idx],
+            blank_voting_ballots: self.blank_voting_ballots[idx],
+            valid_voting_ballots: self.valid_voting_ballots[idx],
+            total_yes: self.total_yes[idx],
+            ratio_yes: self.ratio_yes[idx],
+            total_no: self.total_no[idx],
+            ratio_no: self.ratio_no[idx],
+            cantons_voting_yes: self.cantons_voting_yes[idx],
+            cantons_voting_no: self.cantons_voting_no[idx],
+            outcome: self.outcome[idx],
+            status: self.status[idx],
+            result_status: self.result_status[idx],
+            validated_on: self.validated_on[idx],
+            source_url_it: self.source_url_it[idx].clone(),
+            source_url_de: self.source_url_de[idx].clone(),
+            source_url_fr: self.source_url_fr[idx].clone(),
+            supplementary_url: self.supplementary_url[idx].clone(),
+            fetched_at: self.fetched_at[idx],
+            content_sha256: self.content_sha256[idx].clone(),
+        }
+    }
+
+    // A voting is identified by its number. Scheduled votings may not have one yet and are
+    // matched on their date and titles instead, which may be worded differently once the
+    // results are published. Votings with different numbers never match.
+    pub fn position_of(&self, row: &Row) -> Option<usize> {
+        if let Some(idx) = row
+            .no
+            .and_then(|no| self.no.iter().position(|other| *other == Some(no)))
+        {
+            return Some(idx);
+        }
+
+        let titles = [
+            ("it".to_string(), row.title_it.clone()),
+            ("de".to_string(), row.title_de.clone()),
+            ("fr".to_string(), row.title_fr.clone()),
+        ];
+        match_voting(self, row.date_of_voting?, &titles)
+            .filter(|idx| self.no[*idx].is_none() || row.no.is_none())
+    }
+
+    // Keeps the votings for which `keep` is true, in their order
+    pub fn retain(&mut self, keep: impl Fn(&Row) -> bool) {
+        let rows: Vec<Row> = (0..self.no.len())
+            .map(|idx| self.row(idx))
+            .filter(|row| keep(row))
+            .collect();
+        *self = Data::default();
+        for row in rows {
+            self.update(row);
+        }
+    }
+
+    // Used to update a voting whose results changed since it was extracted
+    pub fn replace(&mut self, idx: usize, row: Row) {
+        self.no[idx] = row.no;
+        self.date_of_voting[idx] = row.date_of_voting;
+        self.title_it[idx] = row.title_it;
+        self.title_fr[idx] = row.title_fr;
+        self.title_de[idx] = row.title_de;
+        self.kind[idx] = row.kind;
+        self.recommendation[idx] = row.recommendation;
+        self.total_voters[idx] = row.total_voters;
+        self.domestic_voters[idx] = row.domestic_voters;
+        self.overseas_voters[idx] = row.overseas_voters;
+        self.ballots_returned[idx] = row.ballots_returned;
+        self.participation[idx] = row.participation;
+        self.invalid_voting_ballots[idx] = row.invalid_voting_ballots;
+        self.blank_voting_ballots[idx] = row.blank_voting_ballots;
+        self.valid_voting_ballots[idx] = row.valid_voting_ballots;
+        self.total_yes[idx] = row.total_yes;
+        self.ratio_yes[idx] = row.ratio_yes;
+        self.total_no[idx] = row.total_no;
+        self.ratio_no[idx] = row.ratio_no;
+        self.cantons_voting_yes[idx] = row.cantons_voting_yes;
+        self.cantons_voting_no[idx] = row.cantons_voting_no;
+        self.outcome[idx] = row.outcome;
+        self.status[idx] = row.status;
+        self.result_status[idx] = row.result_status;
+        self.validated_on[idx] = row.validated_on;
+        self.source_url_it[idx] = row.source_url_it;
+        self.source_url_de[idx] = row.source_url_de;
+        self.source_url_fr[idx] = row.source_url_fr;
+        self.supplementary_url[idx] = row.supplementary_url;
+        self.fetched_at[idx] = row.fetched_at;
+        self.content_sha256[idx] = row.content_sha256;
     }
 }
 
-pub fn create_dataframe_from(data: Data) -> DataFrame {
-    let df: DataFrame = df!(
-        "no" => data.no,
-        DATE_OF_VOTING => data.date_of_voting,
+pub fn create_dataframe_from(data: Data, decimal_ratios: bool) -> DataFrame {
+    // Dates are written in ISO-8601 format, together with the columns derived from them
+    let dates: Vec<Option<String>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.map(|date| date.format("%Y-%m-%d").to_string()))
+        .collect();
+    let years: Vec<Option<i32>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.map(|date| date.year()))
+        .collect();
+    let decades: Vec<Option<i32>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.as_ref().map(date_to_decade))
+        .collect();
+    let quarters: Vec<Option<u32>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.as_ref().map(date_to_quarter))
+        .collect();
+    let weekdays: Vec<Option<String>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.as_ref().map(date_to_weekday))
+        .collect();
+    let electorates: Vec<Option<String>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.as_ref().map(date_to_electorate))
+        .collect();
+    let legislature_periods: Vec<Option<u32>> = data
+        .date_of_voting
+        .iter()
+        .map(|date| date.as_ref().and_then(date_to_legislature_period))
+        .collect();
+
+    let validated_on: Vec<Option<String>> = data
+        .validated_on
+        .iter()
+        .map(|date| date.map(|date| date.format("%Y-%m-%d").to_string()))
+        .collect();
+
+    let fetched_at: Vec<Option<String>> = data
+        .fetched_at
+        .iter()
+        .map(|timestamp| {
+            timestamp.map(|timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Secs, true))
+        })
+        .collect();
+
+    let mut df: DataFrame = df!(
+        NO => data.no,
+        DATE_OF_VOTING => dates,
+        YEAR => years,
+        DECADE => decades,
+        QUARTER => quarters,
+        WEEKDAY => weekdays,
+        LEGISLATURE_PERIOD => legislature_periods,
+        ELECTORATE => electorates,
         TITLE_IT => data.title_it,
         TITLE_FR => data.title_fr,
         TITLE_DE => data.title_de,
-        "kind" => data.kind,
-        "recommendation" => data.recommendation,
+        KIND => data.kind,
+        RECOMMENDATION => data.recommendation,
         TOTAL_VOTERS => data.total_voters,
-        "domestic_voters" => data.domestic_voters,
-        OVERSEAS_VOTERS => data.overseas_voters,
+        DOMESTIC_VOTERS => data.domestic_voters,
+        OVERSEAS_VOTERS => data.overseas_voters.iter().map(Tristate::value).collect::<Vec<_>>(),
+        OVERSEAS_VOTERS_STATUS => statuses(&data.overseas_voters),
         BALLOTS_RETURNED => data.ballots_returned,
-        PARTICIPATION => data.participation,
+        PARTICIPATION => decimals_to_strings(&data.participation),
         INVALID_VOTING_BALLOTS => data.invalid_voting_ballots,
         BLANK_VOTING_BALLOTS => data.blank_voting_ballots,
         VALID_VOTING_BALLOTS => data.valid_voting_ballots,
         TOTAL_YES => data.total_yes,
-        RATIO_YES => data.ratio_yes,
+        RATIO_YES => decimals_to_strings(&data.ratio_yes),
         TOTAL_NO => data.total_no,
-        RATIO_NO => data.ratio_no,
-        CANTONS_VOTING_YES => data.cantons_voting_yes,
-        CANTONS_VOTING_NO => data.cantons_voting_no,
-        OUTCOME => data.outcome,
+        RATIO_NO => decimals_to_strings(&data.ratio_no),
+        CANTONS_VOTING_YES => decimals_to_strings(&values(&data.cantons_voting_yes)),
+        CANTONS_VOTING_NO => decimals_to_strings(&values(&data.cantons_voting_no)),
+        CANTONS_VOTING_STATUS => data.cantons_voting_yes.iter().zip(&data.cantons_voting_no).map(|(yes, no)| cantons_voting_status(*yes, *no)).collect::<Vec<_>>(),
+        OUTCOME => data.outcome.iter().map(|outcome| outcome.map(|outcome| outcome.as_str())).collect::<Vec<_>>(),
+        STATUS => data.status.iter().map(VotingStatus::as_str).collect::<Vec<_>>(),
+        RESULT_STATUS => data.result_status.iter().map(|status| status.map(|status| status.as_str())).collect::<Vec<_>>(),
+        VALIDATED_ON => validated_on,
+        SOURCE_URL_IT => data.source_url_it,
+        SOURCE_URL_DE => data.source_url_de,
+        SOURCE_URL_FR => data.source_url_fr,
+        SUPPLEMENTARY_URL => data.supplementary_url,
+        FETCHED_AT => fetched_at,
+        CONTENT_SHA256 => data.content_sha256,
     )
     .unwrap();
 
+    if decimal_ratios {
+        // Percentages are written as published on the page, followed by their exact fraction
+        for (column, column_fraction, values) in [
+            (PARTICIPATION, PARTICIPATION_FRACTION, &data.participation),
+            (RATIO_YES, RATIO_YES_FRACTION, &data.ratio_yes),
+            (RATIO_NO, RATIO_NO_FRACTION, &data.ratio_no),
+        ] {
+            let published: Vec<Option<String>> = values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_ref()
+                        .map(|value| fraction_to_published_percentage(value).to_string())
+                })
+                .collect();
+            df.with_column(Series::new(column.into(), published))
+                .unwrap();
+
+            let position = df.get_column_index(column).unwrap() + 1;
+            df.insert_column(
+                position,
+                Series::new(column_fraction.into(), decimals_to_strings(values)),
+            )
+            .unwrap();
+        }
+    }
+
     df
 }
 
+fn values<T: Copy>(values: &[Tristate<T>]) -> Vec<Option<T>> {
+    values.iter().map(Tristate::value).collect()
+}
+
+fn statuses<T: Copy>(values: &[Tristate<T>]) -> Vec<&'static str> {
+    values.iter().map(Tristate::status).collect()
+}
+
+// The cantonal votes only have a value when both the yes and the no were found
+pub fn cantons_voting_status(yes: Tristate<Decimal>, no: Tristate<Decimal>) -> &'static str {
+    match (yes, no) {
+        (Tristate::Value(_), Tristate::Value(_)) => "value",
+        (Tristate::NotApplicable, Tristate::NotApplicable) => "not_applicable",
+        _ => "missing",
+    }
+}
+
+// Ratios are written as text from their exact value, which a float would round
+pub fn decimals_to_strings(values: &[Option<Decimal>]) -> Vec<Option<String>> {
+    values
+        .iter()
+        .map(|value| value.map(|value| value.to_string()))
+        .collect()
+}
+
 pub fn save_as_csv(df: &mut DataFrame) {
+    save_dataframe(df, DATASET_FILE);
+}
+
+pub fn save_dataframe(df: &mut DataFrame, path: &str) {
     // Save the DataFrame to a CSV file
-    let mut file = File::create("data.csv").expect("could not create file");
+    let mut file = File::create(path).expect("could not create file");
 
     CsvWriter::new(&mut file)
         .include_header(true)
@@ -127,3 +556,53 @@ pub fn save_as_csv(df: &mut DataFrame) {
         .finish(df)
         .expect("Could not save the dataframe");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(df: &DataFrame, column: &str) -> Vec<String> {
+        df.column(column)
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(|value| value.to_string())
+            .collect()
+    }
+
+    fn data() -> Data {
+        let mut data = Data::default();
+        data.update(Row {
+            no: Some(631),
+            participation: Some(Decimal::new(5910, 4)),
+            ratio_yes: Some(Decimal::new(523, 3)),
+            ratio_no: Some(Decimal::new(477, 3)),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            cantons_voting_no: Tristate::Value(Decimal::new(195, 1)),
+            ..Default::default()
+        });
+        data
+    }
+
+    #[test]
+    fn ratios_are_written_without_float_artifacts() {
+        let df = create_dataframe_from(data(), false);
+        assert_eq!(texts(&df, PARTICIPATION), vec!["0.5910"]);
+        assert_eq!(texts(&df, RATIO_YES), vec!["0.523"]);
+        assert_eq!(texts(&df, RATIO_NO), vec!["0.477"]);
+        assert_eq!(texts(&df, CANTONS_VOTING_YES), vec!["3.5"]);
+        assert_eq!(texts(&df, CANTONS_VOTING_NO), vec!["19.5"]);
+        assert!(df.column(PARTICIPATION_FRACTION).is_err());
+    }
+
+    #[test]
+    fn decimal_ratios_add_the_fractions() {
+        let df = create_dataframe_from(data(), true);
+        assert_eq!(texts(&df, PARTICIPATION), vec!["59.10"]);
+        assert_eq!(texts(&df, PARTICIPATION_FRACTION), vec!["0.5910"]);
+        assert_eq!(texts(&df, RATIO_YES), vec!["52.3"]);
+        assert_eq!(texts(&df, RATIO_YES_FRACTION), vec!["0.523"]);
+        assert_eq!(texts(&df, CANTONS_VOTING_YES), vec!["3.5"]);
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+
+use log::warn;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+
+use crate::constants::{
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_STATUS,
+    CANTONS_VOTING_YES, DATE_OF_VOTING, DOMESTIC_VOTERS, INVALID_VOTING_BALLOTS, KIND, OUTCOME,
+    OVERSEAS_VOTERS, OVERSEAS_VOTERS_STATUS, PARTICIPATION, RATIO_NO, RATIO_YES, RECOMMENDATION,
+    RESULT_STATUS, STATUS, TITLE_DE, TITLE_FR, TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES,
+    VALIDATED_ON, VALID_VOTING_BALLOTS,
+};
+use crate::data::{cantons_voting_status, Data};
+
+pub struct FieldChange {
+    pub no: u32,
+    pub column: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Default)]
+pub struct DatasetDiff {
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+    pub changes: Vec<FieldChange>,
+}
+
+enum Field {
+    Text(Option<String>),
+    Number(Option<Decimal>),
+}
+
+// Fields compared between two releases. Provenance columns are left out on purpose, as they
+// change on every run.
+fn fields_of(data: &Data, idx: usize) -> Vec<(&'static str, Field)> {
+    let integer = |value: Option<u32>| Field::Text(value.map(|value| value.to_string()));
+    vec![
+        (
+            DATE_OF_VOTING,
+            Field::Text(data.date_of_voting[idx].map(|date| date.to_string())),
+        ),
+        (TITLE_IT, Field::Text(Some(data.title_it[idx].clone()))),
+        (TITLE_FR, Field::Text(Some(data.title_fr[idx].clone()))),
+        (TITLE_DE, Field::Text(Some(data.title_de[idx].clone()))),
+        (KIND, Field::Text(Some(data.kind[idx].clone()))),
+        (
+            RECOMMENDATION,
+            Field::Text(data.recommendation[idx].clone()),
+        ),
+        (TOTAL_VOTERS, integer(data.total_voters[idx])),
+        (DOMESTIC_VOTERS, integer(data.domestic_voters[idx])),
+        (OVERSEAS_VOTERS, integer(data.overseas_voters[idx].value())),
+        (
+            OVERSEAS_VOTERS_STATUS,
+            Field::Text(Some(data.overseas_voters[idx].status().to_string())),
+        ),
+        (BALLOTS_RETURNED, integer(data.ballots_returned[idx])),
+        (PARTICIPATION, Field::Number(data.participation[idx])),
+        (
+            INVALID_VOTING_BALLOTS,
+            integer(data.invalid_voting_ballots[idx]),
+        ),
+        (
+            BLANK_VOTING_BALLOTS,
+            integer(data.blank_voting_ballots[idx]),
+        ),
+        (
+            VALID_VOTING_BALLOTS,
+            integer(data.valid_voting_ballots[idx]),
+        ),
+        (TOTAL_YES, integer(data.total_yes[idx])),
+        (RATIO_YES, Field::Number(data.ratio_yes[idx])),
+        (TOTAL_NO, integer(data.total_no[idx])),
+        (RATIO_NO, Field::Number(data.ratio_no[idx])),
+        (
+            CANTONS_VOTING_YES,
+            Field::Number(data.cantons_voting_yes[idx].value()),
+        ),
+        (
+            CANTONS_VOTING_NO,
+            Field::Number(data.cantons_voting_no[idx].value()),
+        ),
+        (
+            CANTONS_VOTING_STATUS,
+            Field::Text(Some(
+                cantons_voting_status(data.cantons_voting_yes[idx], data.cantons_voting_no[idx])
+                    .to_string(),
+            )),
+        ),
+        (
+            OUTCOME,
+            Field::Text(data.outcome[idx].map(|outcome| outcome.to_string())),
+        ),
+        (STATUS, Field::Text(Some(data.status[idx].to_string()))),
+        (
+            RESULT_STATUS,
+            Field::Text(data.result_status[idx].map(|status| status.to_string())),
+        ),
+        (
+            VALIDATED_ON,
+            Field::Text(data.validated_on[idx].map(|date| date.to_string())),
+        ),
+    ]
+}
+
+fn index_by_number(data: &Data) -> HashMap<u32, usize> {
+    let mut index: HashMap<u32, usize> = HashMap::new();
+    for (idx, no) in data.no.iter().enumerate() {
+        match no {
+            Some(no) => {
+                index.insert(*no, idx);
+            }
+            None => warn!(
+                "Voting in row {} has no number and cannot be compared",
+                idx + 1
+            ),
+        }
+    }
+    index
+}
+
+pub fn diff_datasets(old: &Data, new: &Data, tolerance: f64) -> DatasetDiff {
+    let old_index = index_by_number(old);
+    let new_index = index_by_number(new);
+
+    let mut diff = DatasetDiff::default();
+    for no in old_index.keys() {
+        if !new_index.contains_key(no) {
+            diff.removed.push(*no);
+        }
+    }
+
+    for (no, new_idx) in &new_index {
+        let Some(old_idx) = old_index.get(no) else {
+            diff.added.push(*no);
+            continue;
+        };
+
+        for ((column, old_field), (_, new_field)) in fields_of(old, *old_idx)
+            .into_iter()
+            .zip(fields_of(new, *new_idx))
+        {
+            let changed = match (&old_field, &new_field) {
+                (Field::Number(Some(old_value)), Field::Number(Some(new_value))) => {
+                    (*old_value - *new_value).abs().to_f64().unwrap_or(f64::MAX) > tolerance
+                }
+                (Field::Number(old_value), Field::Number(new_value)) => old_value != new_value,
+                (Field::Text(old_value), Field::Text(new_value)) => old_value != new_value,
+                _ => unreachable!("fields are listed in the same order for both datasets"),
+            };
+            if changed {
+                diff.changes.push(FieldChange {
+                    no: *no,
+                    column,
+                    old: field_to_string(old_field),
+                    new: field_to_string(new_field),
+                });
+            }
+        }
+    }
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changes
+        .sort_by(|left, right| left.no.cmp(&right.no).then(left.column.cmp(right.column)));
+    diff
+}
+
+// Values of a voting as text, in the order of the compared fields
+pub fn values_of(data: &Data, idx: usize) -> Vec<(&'static str, Option<String>)> {
+    fields_of(data, idx)
+        .into_iter()
+        .map(|(column, field)| (column, field_to_string(field)))
+        .collect()
+}
+
+fn field_to_string(field: Field) -> Option<String> {
+    match field {
+        Field::Text(value) => value,
+        Field::Number(value) => value.map(|value| value.to_string()),
+    }
+}
+
+pub fn diff_to_text(diff: &DatasetDiff) -> String {
+    let mut lines: Vec<String> = vec![format!(
+        "{} votings added, {} votings removed, {} values changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changes.len()
+    )];
+
+    for no in &diff.added {
+        lines.push(format!("+ voting {}", no));
+    }
+    for no in &diff.removed {
+        lines.push(format!("- voting {}", no));
+    }
+    for change in &diff.changes {
+        lines.push(format!(
+            "~ voting {}, {}: {} -> {}",
+            change.no,
+            change.column,
+            change.old.as_deref().unwrap_or("null"),
+            change.new.as_deref().unwrap_or("null")
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn diff_to_json(diff: &DatasetDiff) -> Value {
+    json!({
+        "added": diff.added,
+        "removed": diff.removed,
+        "changes": diff
+            .changes
+            .iter()
+            .map(|change| json!({
+                "no": change.no,
+                "column": change.column,
+                "old": change.old,
+                "new": change.new,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Row, Tristate};
+
+    use super::*;
+
+    fn data(rows: Vec<Row>) -> Data {
+        let mut data = Data::default();
+        rows.into_iter().for_each(|row| data.update(row));
+        data
+    }
+
+    fn voting(no: u32) -> Row {
+        Row {
+            no: Some(no),
+            kind: "initiative".to_string(),
+            ratio_yes: Some(Decimal::new(3847, 4)),
+            overseas_voters: Tristate::Value(193_460),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            cantons_voting_no: Tristate::Value(Decimal::new(195, 1)),
+            ..Default::default()
+        }
+    }
+
+    fn columns(diff: &DatasetDiff) -> Vec<&'static str> {
+        diff.changes.iter().map(|change| change.column).collect()
+    }
+
+    #[test]
+    fn added_and_removed_votings_are_listed() {
+        let old = data(vec![voting(640), voting(641)]);
+        let new = data(vec![voting(641), voting(642), voting(643)]);
+        let diff = diff_datasets(&old, &new, 0.0);
+        assert_eq!(diff.added, vec![642, 643]);
+        assert_eq!(diff.removed, vec![640]);
+        assert!(diff.changes.is_empty());
+    }
+
+    #[test]
+    fn ratio_changes_within_the_tolerance_are_ignored() {
+        let old = data(vec![voting(640)]);
+        let new = data(vec![Row {
+            ratio_yes: Some(Decimal::new(3848, 4)),
+            ..voting(640)
+        }]);
+        assert!(diff_datasets(&old, &new, 0.001).changes.is_empty());
+
+        let diff = diff_datasets(&old, &new, 0.00001);
+        assert_eq!(columns(&diff), vec![RATIO_YES]);
+        assert_eq!(diff.changes[0].old.as_deref(), Some("0.3847"));
+        assert_eq!(diff.changes[0].new.as_deref(), Some("0.3848"));
+    }
+
+    #[test]
+    fn a_ratio_that_disappears_is_a_change_whatever_the_tolerance() {
+        let old = data(vec![voting(640)]);
+        let new = data(vec![Row {
+            ratio_yes: None,
+            ..voting(640)
+        }]);
+        let diff = diff_datasets(&old, &new, 1.0);
+        assert_eq!(columns(&diff), vec![RATIO_YES]);
+        assert_eq!(diff.changes[0].new, None);
+    }
+
+    #[test]
+    fn status_changes_of_tristates_are_listed() {
+        let old = data(vec![voting(640)]);
+        let new = data(vec![Row {
+            overseas_voters: Tristate::NotApplicable,
+            cantons_voting_yes: Tristate::Missing,
+            cantons_voting_no: Tristate::Missing,
+            ..voting(640)
+        }]);
+        let diff = diff_datasets(&old, &new, 0.0);
+        assert_eq!(
+            columns(&diff),
+            vec![
+                CANTONS_VOTING_NO,
+                CANTONS_VOTING_STATUS,
+                CANTONS_VOTING_YES,
+                OVERSEAS_VOTERS,
+                OVERSEAS_VOTERS_STATUS,
+            ]
+        );
+        let status = &diff.changes[1];
+        assert_eq!(status.old.as_deref(), Some("value"));
+        assert_eq!(status.new.as_deref(), Some("missing"));
+        let overseas_status = &diff.changes[4];
+        assert_eq!(overseas_status.new.as_deref(), Some("not_applicable"));
+    }
+
+    #[test]
+    fn the_json_report_lists_every_difference() {
+        let old = data(vec![voting(640), voting(641)]);
+        let new = data(vec![
+            Row {
+                ratio_yes: None,
+                ..voting(640)
+            },
+            voting(642),
+        ]);
+        let diff = diff_datasets(&old, &new, 0.0);
+        assert_eq!(
+            diff_to_json(&diff),
+            json!({
+                "added": [642],
+                "removed": [641],
+                "changes": [
+                    { "no": 640, "column": RATIO_YES, "old": "0.3847", "new": null },
+                ],
+            })
+        );
+        assert_eq!(
+            diff_to_text(&diff),
+            "1 votings added, 1 votings removed, 1 values changed\n\
+             + voting 642\n\
+             - voting 641\n\
+             ~ voting 640, ratio_yes: 0.3847 -> null"
+        );
+    }
+}
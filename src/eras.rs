@@ -0,0 +1,196 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::constants::{
+    CANTONS_BEFORE_JURA, CANTONS_SINCE_JURA, JURA_SINCE, OVERSEAS_VOTERS_SINCE,
+    WOMEN_SUFFRAGE_SINCE,
+};
+use crate::data::{Row, Tristate};
+use crate::statistics::record_validation_warning;
+
+// Rules of the federal votings that changed over time and affect what a detail page contains
+pub struct Era {
+    // Swiss abroad can vote since the Federal Act on the political rights of the Swiss abroad
+    pub overseas_voters: bool,
+    // Only men could vote before the introduction of women's suffrage in 1971
+    pub women_suffrage: bool,
+    // Number of cantonal votes, half-cantons counting one half
+    pub number_of_cantons: u32,
+}
+
+pub fn era_of(date: Option<NaiveDate>) -> Option<Era> {
+    let date = date?;
+    let since = |(year, month, day): (i32, u32, u32)| {
+        date >= NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    };
+
+    Some(Era {
+        overseas_voters: since(OVERSEAS_VOTERS_SINCE),
+        women_suffrage: since(WOMEN_SUFFRAGE_SINCE),
+        number_of_cantons: match since(JURA_SINCE) {
+            true => CANTONS_SINCE_JURA,
+            false => CANTONS_BEFORE_JURA,
+        },
+    })
+}
+
+pub fn date_to_electorate(date: &NaiveDate) -> String {
+    match era_of(Some(*date)).unwrap().women_suffrage {
+        true => "men and women".to_string(),
+        false => "men".to_string(),
+    }
+}
+
+pub fn overseas_voters_of(value: Option<u32>, era: Option<&Era>) -> Tristate<u32> {
+    match (value, era) {
+        (Some(value), _) => Tristate::Value(value),
+        (None, Some(era)) if !era.overseas_voters => Tristate::NotApplicable,
+        (None, _) => Tristate::Missing,
+    }
+}
+
+pub fn cantons_voting_of(
+    value: Option<Decimal>,
+    double_majority: Option<bool>,
+) -> Tristate<Decimal> {
+    // Votings subject to the optional referendum do not require a majority of the cantons, so
+    // their pages have no cantonal votes
+    match (value, double_majority) {
+        (Some(value), _) => Tristate::Value(value),
+        (None, Some(false)) => Tristate::NotApplicable,
+        (None, _) => Tristate::Missing,
+    }
+}
+
+pub fn validate_cantons(row: &Row, era: Option<&Era>) {
+    if let Some(warning) = cantons_warning(row, era) {
+        record_validation_warning(warning);
+    }
+}
+
+fn cantons_warning(row: &Row, era: Option<&Era>) -> Option<String> {
+    match (row.cantons_voting_yes, row.cantons_voting_no) {
+        (Tristate::Value(yes), Tristate::Value(no)) => {
+            let era = era?;
+            (yes + no != Decimal::from(era.number_of_cantons)).then(|| {
+                format!(
+                    "Cantonal votes of voting {} add up to {} instead of {} cantons",
+                    row.source_url_it,
+                    yes + no,
+                    era.number_of_cantons
+                )
+            })
+        }
+        (Tristate::Value(_), _) | (_, Tristate::Value(_)) => Some(format!(
+            "Only one side of the cantonal votes of voting {} was found",
+            row.source_url_it
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn era_on(year: i32, month: u32, day: u32) -> Era {
+        era_of(NaiveDate::from_ymd_opt(year, month, day)).unwrap()
+    }
+
+    fn row_with_cantons(yes: Tristate<Decimal>, no: Tristate<Decimal>) -> Row {
+        Row {
+            cantons_voting_yes: yes,
+            cantons_voting_no: no,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn women_can_vote_since_march_1971() {
+        assert!(!era_on(1971, 3, 15).women_suffrage);
+        assert!(era_on(1971, 3, 16).women_suffrage);
+        assert_eq!(
+            date_to_electorate(&NaiveDate::from_ymd_opt(1971, 2, 7).unwrap()),
+            "men"
+        );
+    }
+
+    #[test]
+    fn swiss_abroad_can_vote_since_july_1977() {
+        assert!(!era_on(1977, 6, 30).overseas_voters);
+        assert!(era_on(1977, 7, 1).overseas_voters);
+    }
+
+    #[test]
+    fn jura_votes_since_1979() {
+        assert_eq!(era_on(1978, 12, 31).number_of_cantons, CANTONS_BEFORE_JURA);
+        assert_eq!(era_on(1979, 1, 1).number_of_cantons, CANTONS_SINCE_JURA);
+    }
+
+    #[test]
+    fn era_of_an_unknown_date_is_unknown() {
+        assert!(era_of(None).is_none());
+    }
+
+    #[test]
+    fn overseas_voters_are_not_applicable_before_1977() {
+        let before = era_on(1977, 6, 12);
+        let after = era_on(1977, 9, 25);
+        assert_eq!(
+            overseas_voters_of(None, Some(&before)),
+            Tristate::NotApplicable
+        );
+        assert_eq!(overseas_voters_of(None, Some(&after)), Tristate::Missing);
+        assert_eq!(
+            overseas_voters_of(Some(12), Some(&after)),
+            Tristate::Value(12)
+        );
+        assert_eq!(overseas_voters_of(None, None), Tristate::Missing);
+    }
+
+    #[test]
+    fn cantonal_votes_add_up_to_the_cantons_of_the_era() {
+        let before_jura = era_on(1978, 12, 3);
+        let since_jura = era_on(1979, 2, 18);
+        let row = |yes: i64, no: i64| {
+            row_with_cantons(
+                Tristate::Value(Decimal::new(yes, 1)),
+                Tristate::Value(Decimal::new(no, 1)),
+            )
+        };
+
+        assert_eq!(cantons_warning(&row(125, 95), Some(&before_jura)), None);
+        assert!(cantons_warning(&row(125, 95), Some(&since_jura)).is_some());
+        assert_eq!(cantons_warning(&row(135, 95), Some(&since_jura)), None);
+        assert!(cantons_warning(&row(135, 95), Some(&before_jura)).is_some());
+    }
+
+    #[test]
+    fn cantonal_votes_only_apply_to_a_double_majority() {
+        let votes = Some(Decimal::from(20));
+        assert_eq!(
+            cantons_voting_of(votes, Some(false)),
+            Tristate::Value(Decimal::from(20))
+        );
+        assert_eq!(
+            cantons_voting_of(None, Some(false)),
+            Tristate::NotApplicable
+        );
+        assert_eq!(cantons_voting_of(None, Some(true)), Tristate::Missing);
+        assert_eq!(cantons_voting_of(None, None), Tristate::Missing);
+    }
+
+    #[test]
+    fn cantonal_votes_are_reported_when_one_side_is_missing() {
+        let era = era_on(2020, 9, 27);
+        let yes = Tristate::Value(Decimal::from(20));
+        assert!(cantons_warning(&row_with_cantons(yes, Tristate::Missing), Some(&era)).is_some());
+        assert_eq!(
+            cantons_warning(
+                &row_with_cantons(Tristate::NotApplicable, Tristate::NotApplicable),
+                Some(&era)
+            ),
+            None
+        );
+    }
+}
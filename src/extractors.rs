@@ -1,151 +1,631 @@
-use collections::HashMap;
+use collections::{HashMap, HashSet};
 use std::collections;
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use regex::Regex;
-use scraper::Html;
+use chrono::{NaiveDate, Utc};
+use ego_tree::NodeId;
+use log::{debug, warn};
+use scraper::{ElementRef, Html, Node};
+use sha2::{Digest, Sha256};
+use tracing::info_span;
 
+use crate::checkpoint::Checkpoint;
 use crate::constants::{
     BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_YES, DATE_OF_VOTING,
-    INVALID_VOTING_BALLOTS, OUTCOME, OVERSEAS_VOTERS, PARTICIPATION, RATIO_NO, RATIO_YES, TITLE_DE,
-    TITLE_FR, TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, URL_SUMMARY_PAGE_DE,
-    URL_SUMMARY_PAGE_FR, URL_SUMMARY_PAGE_IT, VALID_VOTING_BALLOTS,
+    FETCH_ATTEMPTS, FETCH_RETRY_DELAY_MS, INVALID_VOTING_BALLOTS, OVERSEAS_VOTERS, PARTICIPATION,
+    RATIO_NO, RATIO_YES, RESULT_STATUS_EXPECTED_DAYS, SOURCE_URL_DE, SOURCE_URL_FR, TITLE_DE,
+    TITLE_FR, TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALID_VOTING_BALLOTS,
 };
 use crate::converters::{
-    convert_date_to_us_format, integer_and_fraction_to_f32, ratio_to_f32, string_to_u32,
+    convert_date_to_iso_format, integer_and_fraction_to_decimal, ratio_to_decimal, string_to_u32,
 };
-use crate::data::{Data, Row};
+use crate::data::{Data, Outcome, ResultStatus, Row, Tristate, VotingStatus};
+use crate::eras::{cantons_voting_of, era_of, overseas_voters_of, validate_cantons};
+use crate::profile::{profile, OutcomeSentences};
+use crate::statistics::{
+    record_cache_hit, record_page_fetched, record_retry, record_validation_warning,
+};
+
+// Keys of the outcome sentences of the summary pages in every language
+const OUTCOME_IT: &str = "outcome_it";
+const OUTCOME_DE: &str = "outcome_de";
+const OUTCOME_FR: &str = "outcome_fr";
+
+static PAGE_CACHE: LazyLock<Mutex<HashMap<String, String>>> = LazyLock::new(Default::default);
+
+pub fn execute_extractions_of_data(resume: bool) -> reqwest::Result<Data> {
+    let mut checkpoint = Checkpoint::open(resume);
+    let data = extract_information_from_summary_page().and_then(|results| {
+        let number_of_results = results.get("url").unwrap().len();
+        extract_votings(
+            &results,
+            (0..number_of_results).collect(),
+            Some(&mut checkpoint),
+        )
+    });
+    clear_page_cache();
+    data
+}
+
+pub fn extract_new_or_changed_votings(
+    known_votings: &HashMap<u32, String>,
+    provisional_votings: &HashSet<u32>,
+    recheck_since: NaiveDate,
+) -> reqwest::Result<Data> {
+    let results = extract_information_from_summary_page()?;
+
+    let mut indices: Vec<usize> = Vec::new();
+    for (idx, url) in results.get("url").unwrap().iter().enumerate() {
+        let Some(no) = extract_number_votation_from_url(url) else {
+            debug!("Skipped voting {} without number", url);
+            continue;
+        };
+        let Some(content_sha256) = known_votings.get(&no) else {
+            indices.push(idx);
+            continue;
+        };
+
+        // Results of recent votings may still be completed or corrected, and provisional
+        // results are checked until they are final. The page is cached and therefore not
+        // fetched again if it has to be extracted.
+        let date_of_voting = convert_date_to_iso_format(&results.get(DATE_OF_VOTING).unwrap()[idx]);
+        if (date_of_voting.is_some_and(|date| date >= recheck_since)
+            || provisional_votings.contains(&no))
+            && format!("{:x}", Sha256::digest(fetch_html_content(url)?.as_bytes()))
+                != *content_sha256
+        {
+            indices.push(idx);
+        }
+    }
+    extract_votings(&results, indices, None)
+}
+
+pub fn extract_voting(voting: &str) -> reqwest::Result<Option<Row>> {
+    // A voting is given by its number or by the URL of its detail page in any language
+    let Some(no) = voting
+        .parse::<u32>()
+        .ok()
+        .or_else(|| extract_number_votation_from_url(voting))
+    else {
+        return Ok(None);
+    };
+    let results = extract_information_from_summary_page()?;
+    let Some(idx) = results
+        .get("url")
+        .unwrap()
+        .iter()
+        .position(|url| extract_number_votation_from_url(url) == Some(no))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(extract_votings(&results, vec![idx], None)?.row(0)))
+}
+
+// Cells of a detail page that could not be used, kept with the row in the checkpoint so that the
+// warnings of a resumed run count them as well
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ParsingIssues {
+    pub unparsed_cells: Vec<String>,
+    pub unknown_labels: Vec<String>,
+}
+
+impl ParsingIssues {
+    fn count(
+        &self,
+        unparsed_cells: &mut HashMap<String, u32>,
+        unknown_labels: &mut HashMap<String, u32>,
+    ) {
+        for column in &self.unparsed_cells {
+            *unparsed_cells.entry(column.clone()).or_insert(0) += 1;
+        }
+        for label in &self.unknown_labels {
+            *unknown_labels.entry(label.clone()).or_insert(0) += 1;
+        }
+    }
+}
+
+// Row of a page of upcoming votings, with the date and title of a scheduled object
+struct ScheduledObject {
+    date: String,
+    title: String,
+    href: Option<String>,
+    html: String,
+}
+
+pub fn extract_scheduled_votings() -> reqwest::Result<Data> {
+    let urls = &profile().urls;
+    let objects_it = extract_scheduled_objects(&urls.upcoming_page_it)?;
+    let objects_de = extract_scheduled_objects(&urls.upcoming_page_de)?;
+    let objects_fr = extract_scheduled_objects(&urls.upcoming_page_fr)?;
+
+    let mut data = Data::default();
+    let fetched_at = Utc::now();
+    for (idx, object) in objects_it.iter().enumerate() {
+        let date_of_voting = convert_date_to_iso_format(&object.date);
+        if date_of_voting.is_none() {
+            record_validation_warning(format!(
+                "Rejected malformed date '{}' of scheduled voting '{}'",
+                object.date, object.title
+            ));
+        }
+
+        // The pages in the other languages list the objects in the same order, like the
+        // summary pages, and link to detail pages with the same file name
+        let source_url = |detail_page: &str, upcoming_page: &str| match &object.href {
+            Some(href) => format!("{}{}", detail_page, href),
+            None => upcoming_page.to_string(),
+        };
+        let source_url_it = source_url(&urls.detail_page_it, &urls.upcoming_page_it);
+        data.update(Row {
+            no: extract_number_votation_from_url(&source_url_it),
+            date_of_voting,
+            title_it: object.title.clone(),
+            title_fr: objects_fr
+                .get(idx)
+                .map(|object| object.title.clone())
+                .unwrap_or_default(),
+            title_de: objects_de
+                .get(idx)
+                .map(|object| object.title.clone())
+                .unwrap_or_default(),
+            kind: extract_typology_of_the_voting(object.title.clone()),
+            source_url_it,
+            source_url_de: source_url(&urls.detail_page_de, &urls.upcoming_page_de),
+            source_url_fr: source_url(&urls.detail_page_fr, &urls.upcoming_page_fr),
+            fetched_at: Some(fetched_at),
+            // Hash of the row only, so that a change to another object is not a change of this one
+            content_sha256: format!("{:x}", Sha256::digest(object.html.as_bytes())),
+            status: VotingStatus::Scheduled,
+            ..Default::default()
+        });
+    }
+
+    if objects_de.len() != objects_it.len() || objects_fr.len() != objects_it.len() {
+        record_validation_warning(format!(
+            "Pages of upcoming votings list {} (it), {} (de) and {} (fr) objects",
+            objects_it.len(),
+            objects_de.len(),
+            objects_fr.len()
+        ));
+    }
+    Ok(data)
+}
+
+fn extract_scheduled_objects(url: &str) -> reqwest::Result<Vec<ScheduledObject>> {
+    let document = extract_parsed_html_from(url)?;
 
-pub fn execute_extractions_of_data() -> Data {
-    let results = extract_information_from_summary_page();
+    let row_selector = &profile().selectors.row;
+    let cell_selector = &profile().selectors.cell;
+    let link_selector = &profile().selectors.link;
 
+    // Header rows have no cells, every other row has the date followed by the title
+    let mut objects: Vec<ScheduledObject> = Vec::new();
+    for row in document.select(row_selector) {
+        let cells: Vec<_> = row.select(cell_selector).collect();
+        if cells.len() < 2 {
+            continue;
+        }
+        objects.push(ScheduledObject {
+            date: text_of(&cells[0]),
+            title: text_of(&cells[1]),
+            href: cells[1]
+                .select(link_selector)
+                .next()
+                .and_then(|link| link.value().attr("href"))
+                .map(|href| href.to_string()),
+            html: row.html(),
+        });
+    }
+    Ok(objects)
+}
+
+fn extract_votings(
+    results: &HashMap<&'static str, Vec<String>>,
+    indices: Vec<usize>,
+    mut checkpoint: Option<&mut Checkpoint>,
+) -> reqwest::Result<Data> {
     let mut data: Data = Data::default();
+    let mut unparsed_cells: HashMap<String, u32> = HashMap::new();
+    let mut unknown_labels: HashMap<String, u32> = HashMap::new();
     let mut spinning_circle = progress::SpinningCircle::new();
 
-    let number_of_results = results.get("url").unwrap().len();
-    for idx in 0..number_of_results {
+    let number_of_results = indices.len();
+    for (position, idx) in indices.into_iter().enumerate() {
         spinning_circle.set_job_title(
             format!(
                 "Parsing page: {} ({} of {})",
                 results.get("url").unwrap()[idx],
-                idx,
+                position,
                 number_of_results
             )
             .as_str(),
         );
 
-        let document = extract_parsed_html_from(&results.get("url").unwrap()[idx]);
+        let span = info_span!(
+            "voting",
+            no = extract_number_votation_from_url(&results.get("url").unwrap()[idx]),
+            url = %results.get("url").unwrap()[idx]
+        );
+        let _entered = span.enter();
+
+        if let Some(checkpoint) = checkpoint.as_mut() {
+            if let Some((row, issues)) =
+                checkpoint.take_parsed_row(&results.get("url").unwrap()[idx])
+            {
+                debug!("Resumed from the checkpoint");
+                issues.count(&mut unparsed_cells, &mut unknown_labels);
+                data.update(row);
+                continue;
+            }
+        }
 
-        let mut row: Row = Row::default();
+        let fetched_at = Utc::now();
+        let html_content = fetch_html_content(&results.get("url").unwrap()[idx])?;
+        let content_sha256 = format!("{:x}", Sha256::digest(html_content.as_bytes()));
+        let document = Html::parse_document(&html_content);
 
-        row.no = extract_number_votation_from_url(&results.get("url").unwrap()[idx]);
-        row.date_of_voting = results.get(DATE_OF_VOTING).unwrap()[idx].clone();
+        let date_of_voting = convert_date_to_iso_format(&results.get(DATE_OF_VOTING).unwrap()[idx]);
+        if date_of_voting.is_none() {
+            record_validation_warning(format!(
+                "Rejected malformed date '{}' of voting {}",
+                results.get(DATE_OF_VOTING).unwrap()[idx],
+                results.get("url").unwrap()[idx]
+            ));
+        }
+
+        let mut row: Row = Row {
+            no: extract_number_votation_from_url(&results.get("url").unwrap()[idx]),
+            date_of_voting,
+            ..Default::default()
+        };
+
+        row.source_url_it = results.get("url").unwrap()[idx].clone();
+        row.source_url_de = results.get(SOURCE_URL_DE).unwrap()[idx].clone();
+        row.source_url_fr = results.get(SOURCE_URL_FR).unwrap()[idx].clone();
+        row.fetched_at = Some(fetched_at);
+        row.content_sha256 = content_sha256;
         row.title_it = results.get(TITLE_IT).unwrap()[idx].clone();
         row.title_fr = results.get(TITLE_FR).unwrap()[idx].clone();
         row.title_de = results.get(TITLE_DE).unwrap()[idx].clone();
         row.kind = extract_typology_of_the_voting(results.get(TITLE_IT).unwrap()[idx].clone());
-        row.outcome = extract_outcome(results.get(OUTCOME).unwrap()[idx].clone());
+        row.outcome = extract_outcome(
+            [
+                ("it", &results.get(OUTCOME_IT).unwrap()[idx]),
+                ("de", &results.get(OUTCOME_DE).unwrap()[idx]),
+                ("fr", &results.get(OUTCOME_FR).unwrap()[idx]),
+            ],
+            &results.get("url").unwrap()[idx],
+        );
 
-        let table_data = extract_data_from_table(document.clone());
-        row.recommendation = extract_recommendation(table_data.get("supplementary_information"));
-        row.total_voters = string_to_u32(table_data.get(TOTAL_VOTERS));
-        row.overseas_voters = string_to_u32(table_data.get(OVERSEAS_VOTERS));
+        let mut issues = ParsingIssues::default();
+        let table_data = extract_data_from_table(&document, &mut issues.unknown_labels);
+        row.supplementary_url = table_data.get("supplementary_information").cloned();
+        row.recommendation = extract_recommendation(table_data.get("supplementary_information"))?;
+        (row.result_status, row.validated_on) = extract_result_status(
+            &document,
+            &results.get("url").unwrap()[idx],
+            row.date_of_voting,
+        );
+        row.total_voters = convert_cell(
+            &table_data,
+            TOTAL_VOTERS,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+
+        // Fields that did not exist in every era are not applicable rather than missing
+        let era = era_of(row.date_of_voting);
+        row.overseas_voters = overseas_voters_of(
+            convert_cell(
+                &table_data,
+                OVERSEAS_VOTERS,
+                string_to_u32,
+                &mut issues.unparsed_cells,
+            ),
+            era.as_ref(),
+        );
         row.domestic_voters = extract_domestic_voters(row.total_voters, row.overseas_voters);
-        row.ballots_returned = string_to_u32(table_data.get(BALLOTS_RETURNED));
-        row.participation = ratio_to_f32(table_data.get(PARTICIPATION));
-        row.blank_voting_ballots = string_to_u32(table_data.get(BLANK_VOTING_BALLOTS));
-        row.invalid_voting_ballots = string_to_u32(table_data.get(INVALID_VOTING_BALLOTS));
-        row.valid_voting_ballots = string_to_u32(table_data.get(VALID_VOTING_BALLOTS));
-        row.total_yes = string_to_u32(table_data.get(TOTAL_YES));
-        row.ratio_yes = ratio_to_f32(table_data.get(RATIO_YES));
-        row.total_no = string_to_u32(table_data.get(TOTAL_NO));
-        row.ratio_no = ratio_to_f32(table_data.get(RATIO_NO));
-        row.cantons_voting_yes = integer_and_fraction_to_f32(table_data.get(CANTONS_VOTING_YES));
-        row.cantons_voting_no = integer_and_fraction_to_f32(table_data.get(CANTONS_VOTING_NO));
+        row.ballots_returned = convert_cell(
+            &table_data,
+            BALLOTS_RETURNED,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+        row.participation = convert_cell(
+            &table_data,
+            PARTICIPATION,
+            ratio_to_decimal,
+            &mut issues.unparsed_cells,
+        );
+        row.blank_voting_ballots = convert_cell(
+            &table_data,
+            BLANK_VOTING_BALLOTS,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+        row.invalid_voting_ballots = convert_cell(
+            &table_data,
+            INVALID_VOTING_BALLOTS,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+        row.valid_voting_ballots = convert_cell(
+            &table_data,
+            VALID_VOTING_BALLOTS,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+        row.total_yes = convert_cell(
+            &table_data,
+            TOTAL_YES,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+        row.ratio_yes = convert_cell(
+            &table_data,
+            RATIO_YES,
+            ratio_to_decimal,
+            &mut issues.unparsed_cells,
+        );
+        row.total_no = convert_cell(
+            &table_data,
+            TOTAL_NO,
+            string_to_u32,
+            &mut issues.unparsed_cells,
+        );
+        row.ratio_no = convert_cell(
+            &table_data,
+            RATIO_NO,
+            ratio_to_decimal,
+            &mut issues.unparsed_cells,
+        );
+        let double_majority = requires_double_majority(&row.title_it);
+        row.cantons_voting_yes = cantons_voting_of(
+            convert_cell(
+                &table_data,
+                CANTONS_VOTING_YES,
+                integer_and_fraction_to_decimal,
+                &mut issues.unparsed_cells,
+            ),
+            double_majority,
+        );
+        row.cantons_voting_no = cantons_voting_of(
+            convert_cell(
+                &table_data,
+                CANTONS_VOTING_NO,
+                integer_and_fraction_to_decimal,
+                &mut issues.unparsed_cells,
+            ),
+            double_majority,
+        );
+        validate_cantons(&row, era.as_ref());
 
+        if let Some(checkpoint) = checkpoint.as_mut() {
+            checkpoint.save(&row, &issues);
+        }
+        issues.count(&mut unparsed_cells, &mut unknown_labels);
         data.update(row);
     }
     println!();
-    data
+
+    let mut columns: Vec<_> = unparsed_cells.into_iter().collect();
+    columns.sort();
+    for (column, count) in columns {
+        record_validation_warning(format!(
+            "{} cells of column '{}' could not be parsed",
+            count, column
+        ));
+    }
+
+    let mut labels: Vec<_> = unknown_labels.into_iter().collect();
+    labels.sort();
+    for (label, count) in labels {
+        record_validation_warning(format!(
+            "Unknown label '{}' in the results table of {} votings",
+            label, count
+        ));
+    }
+    Ok(data)
 }
 
-fn extract_parsed_html_from(url: &String) -> Html {
-    let response = reqwest::blocking::get(url.clone());
-    let html_content = response.unwrap().text().unwrap();
+fn convert_cell<T>(
+    table_data: &HashMap<&'static str, String>,
+    column: &'static str,
+    converter: fn(Option<&String>) -> Option<T>,
+    unparsed_cells: &mut Vec<String>,
+) -> Option<T> {
+    let value = converter(table_data.get(column));
 
-    Html::parse_document(&html_content)
+    // Empty cells are missing values, everything else should have been parsed
+    if let Some(cell) = table_data.get(column) {
+        if value.is_none() && !cell.trim().is_empty() {
+            debug!("Could not parse '{}' of column '{}'", cell, column);
+            unparsed_cells.push(column.to_string());
+        }
+    }
+    value
 }
 
-fn extract_information_from_summary_page() -> HashMap<&'static str, Vec<String>> {
-    let document = extract_parsed_html_from(&URL_SUMMARY_PAGE_IT.to_string());
+fn extract_parsed_html_from(url: &str) -> reqwest::Result<Html> {
+    Ok(Html::parse_document(&fetch_html_content(url)?))
+}
+
+// Pages are cached for the time of an extraction, as some are shared by several votings, such as
+// the supplementary information, or fetched twice, such as the detail pages checked by `watch`
+pub fn clear_page_cache() {
+    PAGE_CACHE.lock().unwrap().clear();
+}
 
-    let row_selector = scraper::Selector::parse("tr").unwrap();
-    let link_selector = scraper::Selector::parse("a").unwrap();
+// Pages that could not be fetched after every attempt are an error of the caller, e.g. `watch`
+// skips the poll and tries again at the next one
+pub fn fetch_html_content(url: &str) -> reqwest::Result<String> {
+    let mut cache = PAGE_CACHE.lock().unwrap();
+    if let Some(content) = cache.get(url) {
+        debug!("Page {} served from cache", url);
+        record_cache_hit();
+        return Ok(content.clone());
+    }
+
+    let started = Instant::now();
+    let mut attempt = 1;
+    let content = loop {
+        match reqwest::blocking::get(url).and_then(|response| response.error_for_status()?.text()) {
+            Ok(content) => break content,
+            Err(error) if attempt < FETCH_ATTEMPTS => {
+                warn!(
+                    "Attempt {} of {} to fetch {} failed: {}",
+                    attempt, FETCH_ATTEMPTS, url, error
+                );
+                record_retry();
+                thread::sleep(Duration::from_millis(FETCH_RETRY_DELAY_MS * attempt as u64));
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    };
+    record_page_fetched(url, started.elapsed());
+
+    cache.insert(url.to_string(), content.clone());
+    Ok(content)
+}
+
+fn extract_information_from_summary_page() -> reqwest::Result<HashMap<&'static str, Vec<String>>> {
+    let profile = profile();
+    let document = extract_parsed_html_from(&profile.urls.summary_page_it)?;
+
+    let row_selector = &profile.selectors.row;
+    let link_selector = &profile.selectors.link;
 
     // Store results in a vector of tuples
     let mut data: HashMap<&str, Vec<String>> = HashMap::from([
         ("url", Vec::new()),
+        (SOURCE_URL_DE, Vec::new()),
+        (SOURCE_URL_FR, Vec::new()),
         (DATE_OF_VOTING, Vec::new()),
-        (OUTCOME, Vec::new()),
     ]);
 
-    data.insert(TITLE_IT, extract_title(URL_SUMMARY_PAGE_IT));
-    data.insert(TITLE_DE, extract_title(URL_SUMMARY_PAGE_DE));
-    data.insert(TITLE_FR, extract_title(URL_SUMMARY_PAGE_FR));
+    let outcomes = &profile.outcomes;
+    for (url, title, outcome, sentences) in [
+        (
+            &profile.urls.summary_page_it,
+            TITLE_IT,
+            OUTCOME_IT,
+            &outcomes.it,
+        ),
+        (
+            &profile.urls.summary_page_de,
+            TITLE_DE,
+            OUTCOME_DE,
+            &outcomes.de,
+        ),
+        (
+            &profile.urls.summary_page_fr,
+            TITLE_FR,
+            OUTCOME_FR,
+            &outcomes.fr,
+        ),
+    ] {
+        let (titles, outcome_sentences) = extract_titles_and_outcomes(url, sentences)?;
+        data.insert(title, titles);
+        data.insert(outcome, outcome_sentences);
+    }
 
     // Iterate over rows (skip the first row, which is the header)
-    for row in document.select(&row_selector).skip(1) {
-        let columns: Vec<_> = row.select(&link_selector).collect();
+    for row in document.select(row_selector).skip(1) {
+        let columns: Vec<_> = row.select(link_selector).collect();
 
         // Extract values if the structure matches
         if columns.len() >= 2 {
-            data.get_mut("url").unwrap().push(format!(
-                "{}{}",
-                "https://www.bk.admin.ch/ch/i/pore/va/",
-                columns[1]
-                    .value()
-                    .attr("href")
-                    .unwrap_or_default()
-                    .to_string()
-            ));
-            data.get_mut(DATE_OF_VOTING).unwrap().push(
-                convert_date_to_us_format(columns[0].text().collect::<String>().trim()).unwrap(),
-            );
-
-            let mut esito = String::new();
-            for element in row.text().collect::<Vec<_>>() {
-                if element.starts_with("L'oggetto") {
-                    esito = element.trim().to_string();
-                    break;
-                }
-            }
-            data.get_mut(OUTCOME).unwrap().push(esito);
+            // The detail pages in the other languages share the same file name
+            let href = columns[1].value().attr("href").unwrap_or_default();
+            data.get_mut("url")
+                .unwrap()
+                .push(format!("{}{}", profile.urls.detail_page_it, href));
+            data.get_mut(SOURCE_URL_DE)
+                .unwrap()
+                .push(format!("{}{}", profile.urls.detail_page_de, href));
+            data.get_mut(SOURCE_URL_FR)
+                .unwrap()
+                .push(format!("{}{}", profile.urls.detail_page_fr, href));
+            data.get_mut(DATE_OF_VOTING)
+                .unwrap()
+                .push(columns[0].text().collect::<String>().trim().to_string());
         }
     }
-    data
+    Ok(data)
 }
 
-fn extract_title(url: &str) -> Vec<String> {
-    let document = extract_parsed_html_from(&url.to_string());
+fn extract_titles_and_outcomes(
+    url: &str,
+    sentences: &OutcomeSentences,
+) -> reqwest::Result<(Vec<String>, Vec<String>)> {
+    let document = extract_parsed_html_from(url)?;
+    Ok(titles_and_outcomes_of(&document, sentences))
+}
 
-    let row_selector = scraper::Selector::parse("tr").unwrap();
-    let link_selector = scraper::Selector::parse("a").unwrap();
+fn titles_and_outcomes_of(
+    document: &Html,
+    sentences: &OutcomeSentences,
+) -> (Vec<String>, Vec<String>) {
+    let row_selector = &profile().selectors.row;
+    let link_selector = &profile().selectors.link;
+    let cell_selector = &profile().selectors.cell;
 
     let mut titles: Vec<String> = Vec::new();
-    for row in document.select(&row_selector).skip(1) {
-        let columns: Vec<_> = row.select(&link_selector).collect();
+    let mut outcomes: Vec<String> = Vec::new();
+    for row in document.select(row_selector).skip(1) {
+        let columns: Vec<_> = row.select(link_selector).collect();
         if columns.len() >= 2 {
             titles.push(columns[1].text().collect::<String>().trim().to_string());
+
+            // The outcome is in the last cell of the row. Titles may start like an outcome
+            // sentence, e.g. "Il controprogetto dell'Assemblea federale", hence the text of the
+            // links is left out.
+            let outcome = row
+                .select(cell_selector)
+                .last()
+                .map(|cell| {
+                    cell.descendants()
+                        .filter(|node| {
+                            !node
+                                .ancestors()
+                                .filter_map(ElementRef::wrap)
+                                .any(|element| link_selector.matches(&element))
+                        })
+                        .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+                        .collect::<String>()
+                })
+                .map(|text| normalise_sentence(&text))
+                .filter(|text| {
+                    sentences
+                        .prefixes
+                        .iter()
+                        .any(|prefix| text.starts_with(&normalise_sentence(prefix)))
+                })
+                .unwrap_or_default();
+            outcomes.push(outcome);
         }
     }
-    titles
+    (titles, outcomes)
 }
 
-fn extract_number_votation_from_url(voting_hyperlink: &String) -> Option<u32> {
-    // Define the regex pattern to capture the number after "det"
-    let re = Regex::new(r"det(\d+)\.html").unwrap();
+fn normalise_sentence(sentence: &str) -> String {
+    // The pages use typographic and ASCII apostrophes alike
+    sentence
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('’', "'")
+}
+
+fn extract_number_votation_from_url(voting_hyperlink: &str) -> Option<u32> {
+    // The pattern captures the number in the file name of the detail page, e.g. "det123.html"
+    let re = &profile().summary_page.voting_number;
 
     // Check if the regex matches and extract the number
-    if let Some(captures) = re.captures(voting_hyperlink.as_str()) {
+    if let Some(captures) = re.captures(voting_hyperlink) {
         if let Some(number) = captures.get(1) {
             return string_to_u32(Some(&number.as_str().to_string()));
         }
@@ -154,115 +634,220 @@ fn extract_number_votation_from_url(voting_hyperlink: &String) -> Option<u32> {
 }
 
 fn extract_typology_of_the_voting(title: String) -> String {
-    if title.contains("Iniziativa") {
-        "initiative".to_string()
-    } else if title.contains("Decreto") {
-        "decree".to_string()
-    } else if title.contains("Legge") {
-        "referendum".to_string()
-    } else if title.contains("Controprogetto") {
-        "counterproposal".to_string()
-    } else {
-        "".to_string()
-    }
+    profile()
+        .kinds
+        .iter()
+        .find(|kind| title.contains(&kind.keyword))
+        .map(|kind| kind.kind.clone())
+        .unwrap_or_default()
 }
 
-fn extract_outcome(outcome: String) -> Option<String> {
-    match outcome.as_str() {
-        "L'oggetto è stato accettato" => Some("accepted".to_string()),
-        "L'oggetto è stato respinto" => Some("not accepted".to_string()),
-        _ => None,
-    }
+// Unknown when the kind of the voting is not recognised
+fn requires_double_majority(title: &str) -> Option<bool> {
+    let kind = profile()
+        .kinds
+        .iter()
+        .find(|kind| title.contains(&kind.keyword))?;
+    let optional_referendum = kind
+        .optional_referendum
+        .iter()
+        .any(|keyword| title.contains(keyword));
+    Some(kind.double_majority && !optional_referendum)
 }
 
-fn extract_data_from_table(document: Html) -> HashMap<&'static str, String> {
-    // Selector for all td elements
-    let td_selector = scraper::Selector::parse("td").unwrap();
-
-    // Variables to store extracted values
-    let mut table_elements: Vec<String> = vec![];
+fn extract_outcome(sentences: [(&str, &str); 3], url: &str) -> Option<Outcome> {
+    let outcomes = &profile().outcomes;
+    let mut parsed: Vec<Outcome> = Vec::new();
+    for ((language, sentence), known_sentences) in
+        sentences
+            .into_iter()
+            .zip([&outcomes.it, &outcomes.de, &outcomes.fr])
+    {
+        // Votings that did not take place yet have no outcome
+        if sentence.is_empty() {
+            continue;
+        }
+        let outcome = known_sentences
+            .sentences
+            .iter()
+            .find(|(known_sentence, _)| normalise_sentence(known_sentence) == sentence)
+            .and_then(|(_, outcome)| outcome.parse::<Outcome>().ok());
+        match outcome {
+            Some(outcome) => parsed.push(outcome),
+            None => record_validation_warning(format!(
+                "Unknown outcome sentence '{}' ({}) of voting {}",
+                sentence, language, url
+            )),
+        }
+    }
 
-    // Iterate through each td element
-    for element in document.select(&td_selector) {
-        table_elements.push(
-            element
-                .text()
+    // The Italian sentence is preferred when the languages disagree
+    if parsed.iter().any(|outcome| *outcome != parsed[0]) {
+        record_validation_warning(format!(
+            "Outcome sentences of voting {} disagree: {}",
+            url,
+            parsed
+                .iter()
+                .map(Outcome::as_str)
                 .collect::<Vec<_>>()
-                .join("")
-                .trim()
-                .to_string(),
-        );
+                .join(", ")
+        ));
     }
+    parsed.first().copied()
+}
+
+fn columns_of_label(label: &str) -> Option<&'static [String]> {
+    profile()
+        .table_labels
+        .iter()
+        .find(|table_label| table_label.label == label)
+        .map(|table_label| table_label.columns.as_slice())
+}
+
+fn text_of(element: &ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join("")
+        .trim()
+        .to_string()
+}
+
+// Role of a cell of a detail page when the results table is parsed
+pub enum CellRole {
+    Label,
+    Value(&'static str),
+    UnknownLabel,
+    Ignored(&'static str),
+}
+
+// Rows of a table as their cells, leaving out the rows of nested tables
+fn rows_of(table: ElementRef) -> Vec<Vec<ElementRef>> {
+    let tr_selector = &profile().selectors.row;
+    table
+        .select(tr_selector)
+        .filter(|row| {
+            row.ancestors()
+                .find(|node| matches!(node.value(), Node::Element(element) if element.name() == "table"))
+                .map(|node| node.id())
+                == Some(table.id())
+        })
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .collect()
+        })
+        .collect()
+}
+
+fn normalise_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Position of the label of a row. Labels such as "bianche" and "nulle" may follow a heading
+// spanning several rows (e.g. "Schede"), so the first known label of the row is its label.
+fn label_position(cells: &[ElementRef]) -> Option<usize> {
+    cells
+        .iter()
+        .position(|cell| columns_of_label(&normalise_label(&text_of(cell))).is_some())
+}
+
+pub fn parse_results_table(
+    document: &Html,
+) -> (HashMap<&'static str, String>, HashMap<NodeId, CellRole>) {
+    // The results table is the one with the most known labels, other tables of the page (e.g.
+    // the navigation) may contain the same words
+    let table_selector = &profile().selectors.table;
+    let results_table = document
+        .select(table_selector)
+        .map(rows_of)
+        .max_by_key(|rows| {
+            rows.iter()
+                .filter(|cells| label_position(cells).is_some())
+                .count()
+        })
+        .unwrap_or_default();
 
-    let mut position: usize = 0;
     let mut data: HashMap<&str, String> = HashMap::new();
-    while position < table_elements.len() {
-        match table_elements[position].as_str() {
-            "Totale elettori" => {
-                position += 1;
-                data.insert(TOTAL_VOTERS, table_elements[position].clone());
-            }
-            "di cui Svizzeri all'estero" => {
-                position += 1;
-                data.insert(OVERSEAS_VOTERS, table_elements[position].clone());
-            }
-            "Bollettini rientrati" => {
-                position += 1;
-                data.insert(BALLOTS_RETURNED, table_elements[position].clone());
-            }
-            "Partecipazione" => {
-                position += 1;
-                data.insert(PARTICIPATION, table_elements[position].clone());
-            }
-            "bianche" => {
-                position += 1;
-                data.insert(BLANK_VOTING_BALLOTS, table_elements[position].clone());
-            }
-            "nulle" => {
-                position += 1;
-                data.insert(INVALID_VOTING_BALLOTS, table_elements[position].clone());
-            }
-            "Schede di voto valide" => {
-                position += 1;
-                data.insert(VALID_VOTING_BALLOTS, table_elements[position].clone());
-            }
-            "Sì" => {
-                position += 1;
-                data.insert(TOTAL_YES, table_elements[position].clone());
+    let mut roles: HashMap<NodeId, CellRole> = HashMap::new();
+    for cells in results_table {
+        let position = label_position(&cells).unwrap_or(0);
+        let Some((label_cell, value_cells)) = cells[position..].split_first() else {
+            continue;
+        };
+        for cell in &cells[..position] {
+            roles.insert(cell.id(), CellRole::Ignored("heading of the labels"));
+        }
+        let label = normalise_label(&text_of(label_cell));
+        let values: Vec<String> = value_cells.iter().map(text_of).collect();
 
-                position += 1;
-                data.insert(RATIO_YES, table_elements[position].clone());
+        // Headings and separators have no value next to their label
+        if label.is_empty() || values.iter().all(String::is_empty) {
+            for cell in &cells {
+                roles.insert(cell.id(), CellRole::Ignored("row without value"));
             }
-            "No" => {
-                position += 1;
-                data.insert(TOTAL_NO, table_elements[position].clone());
+            continue;
+        }
 
-                position += 1;
-                data.insert(RATIO_NO, table_elements[position].clone());
-            }
-            "Voto dei Cantoni sì" => {
-                position += 1;
-                data.insert(CANTONS_VOTING_YES, table_elements[position].clone());
+        let Some(columns) = columns_of_label(&label) else {
+            debug!("Unknown label '{}' in the results table", label);
+            roles.insert(label_cell.id(), CellRole::UnknownLabel);
+            for cell in value_cells {
+                roles.insert(cell.id(), CellRole::Ignored("value of an unknown label"));
             }
-            "Voto dei Cantoni no" => {
-                position += 1;
-                data.insert(CANTONS_VOTING_NO, table_elements[position].clone());
+            continue;
+        };
+
+        if values.len() < columns.len() {
+            debug!(
+                "Row '{}' of the results table has {} values instead of {}",
+                label,
+                values.len(),
+                columns.len()
+            );
+        }
+        roles.insert(label_cell.id(), CellRole::Label);
+        for (position, (cell, value)) in value_cells.iter().zip(values).enumerate() {
+            match columns.get(position) {
+                Some(column) => {
+                    data.insert(column.as_str(), value);
+                    roles.insert(cell.id(), CellRole::Value(column.as_str()));
+                }
+                None => {
+                    roles.insert(
+                        cell.id(),
+                        CellRole::Ignored("no column left for this value"),
+                    );
+                }
             }
-            _ => {}
         }
-        position += 1;
+    }
+    (data, roles)
+}
+
+fn extract_data_from_table(
+    document: &Html,
+    unknown_labels: &mut Vec<String>,
+) -> HashMap<&'static str, String> {
+    let (mut data, roles) = parse_results_table(document);
+    for (id, role) in roles {
+        if let CellRole::UnknownLabel = role {
+            let cell = ElementRef::wrap(document.tree.get(id).unwrap()).unwrap();
+            unknown_labels.push(normalise_label(&text_of(&cell)));
+        }
     }
 
-    let selector = scraper::Selector::parse("a").unwrap();
+    let selector = &profile().selectors.link;
 
     // Find the link with the desired text
-    for element in document.select(&selector) {
+    for element in document.select(selector) {
         if let Some(text) = element.text().next() {
-            if text.trim() == "Informazioni supplementari sull'iniziativa popolare" {
+            if text.trim() == profile().supplementary_information.link_text {
                 if let Some(href) = element.value().attr("href") {
                     data.insert(
                         "supplementary_information",
-                        format!("{}{}", "https://www.bk.admin.ch", href.to_string(),),
+                        format!("{}{}", profile().urls.site, href),
                     );
                     break;
                 }
@@ -272,38 +857,350 @@ fn extract_data_from_table(document: Html) -> HashMap<&'static str, String> {
     data
 }
 
-fn extract_recommendation(url: Option<&String>) -> Option<String> {
-    if url.is_none() {
-        return None;
-    }
-    let supplementary_info = extract_parsed_html_from(url.unwrap());
-    let td_selector = scraper::Selector::parse("td").unwrap();
+fn extract_recommendation(url: Option<&String>) -> reqwest::Result<Option<String>> {
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    let supplementary_info = extract_parsed_html_from(url)?;
+    let td_selector = &profile().selectors.cell;
 
-    // Iterate over all <td> elements to find the one containing "Raccomandazione"
-    for element in supplementary_info.select(&td_selector) {
+    // Iterate over all cells to find the one containing the recommendation
+    for element in supplementary_info.select(td_selector) {
         let text = element
             .text()
             .collect::<Vec<_>>()
             .join(" ")
             .trim()
             .to_string();
-        if text.contains("Raccomandazione") {
-            if text.contains("Rigetto") {
-                return Some("reject".to_string());
+        let supplementary_information = &profile().supplementary_information;
+        if text.contains(&supplementary_information.recommendation_label) {
+            if text.contains(&supplementary_information.rejection_keyword) {
+                return Ok(Some("reject".to_string()));
             } else {
-                return Some("accept".to_string());
+                return Ok(Some("accept".to_string()));
             }
         }
     }
-    None
+    Ok(None)
+}
+
+fn extract_result_status(
+    document: &Html,
+    url: &str,
+    date_of_voting: Option<NaiveDate>,
+) -> (Option<ResultStatus>, Option<NaiveDate>) {
+    let markers = &profile().result_status;
+    let text = normalise_sentence(&document.root_element().text().collect::<Vec<_>>().join(" "));
+
+    // A validated page may still mention the provisional results, hence the date comes first
+    if let Some(date) = markers
+        .validation_date
+        .captures(&text)
+        .and_then(|captures| captures.get(1))
+    {
+        let validated_on = convert_date_to_iso_format(date.as_str());
+        if validated_on.is_none() {
+            record_validation_warning(format!(
+                "Rejected malformed validation date '{}' of voting {}",
+                date.as_str(),
+                url
+            ));
+        }
+        return (Some(ResultStatus::Final), validated_on);
+    }
+
+    let provisional = markers
+        .provisional_markers
+        .iter()
+        .any(|marker| text.contains(&normalise_sentence(marker)));
+    if provisional {
+        return (Some(ResultStatus::Provisional), None);
+    }
+
+    // Older pages show neither, recent ones should, unless the wording of the page changed
+    let since = Utc::now().date_naive() - chrono::Duration::days(RESULT_STATUS_EXPECTED_DAYS);
+    if date_of_voting.is_some_and(|date| date >= since) {
+        record_validation_warning(format!(
+            "Neither provisional results nor a validation date found on the page of voting {}",
+            url
+        ));
+    }
+    (None, None)
 }
 
 fn extract_domestic_voters(
     total_voters: Option<u32>,
-    overseas_voters: Option<u32>,
+    overseas_voters: Tristate<u32>,
 ) -> Option<u32> {
-    if !total_voters.is_none() && !overseas_voters.is_none() {
-        return Some(total_voters.unwrap() + overseas_voters.unwrap());
+    // Before the Swiss abroad could vote, every voter lived in Switzerland
+    match overseas_voters {
+        Tristate::Value(overseas_voters) => total_voters?.checked_sub(overseas_voters),
+        Tristate::NotApplicable => total_voters,
+        Tristate::Missing => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn parse_fixture(html: &str) -> HashMap<&'static str, String> {
+        parse_results_table(&Html::parse_document(html)).0
+    }
+
+    fn cell<'a>(data: &'a HashMap<&'static str, String>, column: &str) -> Option<&'a str> {
+        data.get(column).map(String::as_str)
+    }
+
+    #[test]
+    fn results_table_before_womens_suffrage() {
+        let data = parse_fixture(include_str!("../tests/fixtures/det_19700607.html"));
+        assert_eq!(cell(&data, TOTAL_VOTERS), Some("1'574'947"));
+        assert_eq!(cell(&data, OVERSEAS_VOTERS), None);
+        assert_eq!(cell(&data, BALLOTS_RETURNED), Some("1'218'626"));
+        assert_eq!(cell(&data, PARTICIPATION), Some("77,38%"));
+        // "bianche" follows the heading "Schede" spanning its row and the one of "nulle"
+        assert_eq!(cell(&data, BLANK_VOTING_BALLOTS), Some("4'979"));
+        assert_eq!(cell(&data, INVALID_VOTING_BALLOTS), Some("1'345"));
+        assert_eq!(cell(&data, VALID_VOTING_BALLOTS), Some("1'212'302"));
+        assert_eq!(cell(&data, TOTAL_YES), Some("557'714"));
+        assert_eq!(cell(&data, RATIO_YES), Some("46,0%"));
+        assert_eq!(cell(&data, TOTAL_NO), Some("654'588"));
+        assert_eq!(cell(&data, RATIO_NO), Some("54,0%"));
+        assert_eq!(
+            integer_and_fraction_to_decimal(data.get(CANTONS_VOTING_YES)),
+            Some(Decimal::new(65, 1))
+        );
+        assert_eq!(
+            integer_and_fraction_to_decimal(data.get(CANTONS_VOTING_NO)),
+            Some(Decimal::new(155, 1))
+        );
+    }
+
+    #[test]
+    fn results_table_of_a_referendum() {
+        let data = parse_fixture(include_str!("../tests/fixtures/det_19850922.html"));
+        // The label is split over two lines
+        assert_eq!(cell(&data, TOTAL_VOTERS), Some("4'122'430"));
+        assert_eq!(cell(&data, BLANK_VOTING_BALLOTS), Some("22'034"));
+        assert_eq!(cell(&data, INVALID_VOTING_BALLOTS), Some("3'211"));
+        assert_eq!(cell(&data, VALID_VOTING_BALLOTS), Some("1'672'077"));
+        assert_eq!(cell(&data, TOTAL_YES), Some("921'743"));
+        assert_eq!(cell(&data, RATIO_NO), Some("44,9%"));
+        assert_eq!(cell(&data, CANTONS_VOTING_YES), None);
+        assert_eq!(cell(&data, CANTONS_VOTING_NO), None);
+    }
+
+    #[test]
+    fn results_table_with_the_swiss_abroad() {
+        let document = Html::parse_document(include_str!("../tests/fixtures/det_20200927.html"));
+        let (data, roles) = parse_results_table(&document);
+        // The navigation table also contains "Sì", "No" and "Partecipazione"
+        assert_eq!(string_to_u32(data.get(TOTAL_VOTERS)), Some(5_479_123));
+        assert_eq!(string_to_u32(data.get(OVERSEAS_VOTERS)), Some(193_460));
+        assert_eq!(
+            ratio_to_decimal(data.get(PARTICIPATION)),
+            Some(Decimal::new(5952, 4))
+        );
+        assert_eq!(string_to_u32(data.get(BLANK_VOTING_BALLOTS)), Some(31_204));
+        assert_eq!(string_to_u32(data.get(INVALID_VOTING_BALLOTS)), Some(7_796));
+        assert_eq!(string_to_u32(data.get(TOTAL_NO)), Some(1_988_349));
+        assert_eq!(
+            ratio_to_decimal(data.get(RATIO_YES)),
+            Some(Decimal::new(383, 3))
+        );
+        assert_eq!(
+            integer_and_fraction_to_decimal(data.get(CANTONS_VOTING_NO)),
+            Some(Decimal::new(195, 1))
+        );
+
+        let unknown_labels: Vec<String> = roles
+            .iter()
+            .filter(|(_, role)| matches!(role, CellRole::UnknownLabel))
+            .map(|(id, _)| text_of(&ElementRef::wrap(document.tree.get(*id).unwrap()).unwrap()))
+            .collect();
+        assert_eq!(unknown_labels, vec!["Totale dei voti espressi"]);
+
+        let data = extract_data_from_table(&document, &mut Vec::new());
+        assert_eq!(
+            cell(&data, "supplementary_information"),
+            Some("https://www.bk.admin.ch/ch/i/pore/vi/vis476.html")
+        );
+    }
+
+    #[test]
+    fn pages_that_cannot_be_fetched_are_an_error() {
+        // Nothing listens on port 1, so every attempt is refused
+        let url = "http://127.0.0.1:1/det1.html";
+        assert!(fetch_html_content(url).is_err());
+        assert!(!PAGE_CACHE.lock().unwrap().contains_key(url));
+    }
+
+    fn outcomes_of_fixtures() -> Vec<[(&'static str, String); 3]> {
+        let outcomes = &profile().outcomes;
+        let [it, de, fr] = [
+            (
+                include_str!("../tests/fixtures/summary_it.html"),
+                &outcomes.it,
+            ),
+            (
+                include_str!("../tests/fixtures/summary_de.html"),
+                &outcomes.de,
+            ),
+            (
+                include_str!("../tests/fixtures/summary_fr.html"),
+                &outcomes.fr,
+            ),
+        ]
+        .map(|(html, sentences)| titles_and_outcomes_of(&Html::parse_document(html), sentences).1);
+        (0..it.len())
+            .map(|idx| {
+                [
+                    ("it", it[idx].clone()),
+                    ("de", de[idx].clone()),
+                    ("fr", fr[idx].clone()),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn outcome_sentences_are_read_from_the_outcome_cell() {
+        let outcomes = outcomes_of_fixtures();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(
+            outcomes[0].clone().map(|(_, sentence)| sentence),
+            [
+                "L'oggetto è stato accettato",
+                "Die Vorlage wurde angenommen",
+                "L'objet a été accepté"
+            ]
+        );
+        // The titles of the counter-proposal start like an outcome sentence
+        assert_eq!(
+            outcomes[1].clone().map(|(_, sentence)| sentence),
+            [
+                "L'oggetto è stato respinto",
+                "Die Vorlage wurde abgelehnt",
+                "L'objet a été rejeté"
+            ]
+        );
+    }
+
+    #[test]
+    fn outcomes_are_parsed_in_every_language() {
+        let parse = |sentences: &[(&'static str, String); 3]| {
+            extract_outcome(
+                sentences
+                    .each_ref()
+                    .map(|(language, sentence)| (*language, sentence.as_str())),
+                "summary page",
+            )
+        };
+        let outcomes = outcomes_of_fixtures();
+        assert_eq!(parse(&outcomes[0]), Some(Outcome::Accepted));
+        assert_eq!(parse(&outcomes[1]), Some(Outcome::Rejected));
+        // Tie-break question
+        assert_eq!(parse(&outcomes[2]), Some(Outcome::InitiativePreferred));
+        // Voting that did not take place yet
+        assert_eq!(parse(&outcomes[3]), None);
+    }
+
+    fn result_status_of(
+        body: &str,
+        date: Option<NaiveDate>,
+    ) -> (Option<ResultStatus>, Option<NaiveDate>) {
+        let html = format!(
+            "<html><body><table><tr><td>{}</td></tr></table></body></html>",
+            body
+        );
+        extract_result_status(&Html::parse_document(&html), "det1.html", date)
+    }
+
+    #[test]
+    fn result_status_is_read_from_the_markers() {
+        let date = NaiveDate::from_ymd_opt(2020, 9, 27);
+        assert_eq!(
+            result_status_of("Risultati provvisori", date),
+            (Some(ResultStatus::Provisional), None)
+        );
+        // Validated pages may still mention the provisional results
+        assert_eq!(
+            result_status_of(
+                "Risultati provvisori. Decreto del Consiglio federale del 4.11.2020",
+                date
+            ),
+            (
+                Some(ResultStatus::Final),
+                NaiveDate::from_ymd_opt(2020, 11, 4)
+            )
+        );
+    }
+
+    #[test]
+    fn result_status_is_unknown_without_markers() {
+        assert_eq!(
+            result_status_of("Totale elettori", NaiveDate::from_ymd_opt(1970, 6, 7)),
+            (None, None)
+        );
+        assert_eq!(
+            result_status_of("Totale elettori", Some(Utc::now().date_naive())),
+            (None, None)
+        );
+    }
+
+    fn domestic_voters_on(date: (i32, u32, u32), total: u32, overseas: Option<u32>) -> Option<u32> {
+        let era = era_of(NaiveDate::from_ymd_opt(date.0, date.1, date.2));
+        extract_domestic_voters(Some(total), overseas_voters_of(overseas, era.as_ref()))
+    }
+
+    #[test]
+    fn domestic_voters_are_all_voters_before_the_swiss_abroad_could_vote() {
+        assert_eq!(
+            domestic_voters_on((1977, 6, 12), 3_863_169, None),
+            Some(3_863_169)
+        );
+    }
+
+    #[test]
+    fn domestic_voters_exclude_the_swiss_abroad() {
+        assert_eq!(
+            domestic_voters_on((2020, 9, 27), 5_479_123, Some(193_460)),
+            Some(5_285_663)
+        );
+    }
+
+    #[test]
+    fn domestic_voters_are_unknown_without_the_swiss_abroad() {
+        assert_eq!(domestic_voters_on((2020, 9, 27), 5_479_123, None), None);
+        assert_eq!(extract_domestic_voters(None, Tristate::NotApplicable), None);
+        // More voters abroad than in total cannot be right
+        assert_eq!(extract_domestic_voters(Some(10), Tristate::Value(11)), None);
+    }
+
+    #[test]
+    fn decrees_approving_a_treaty_need_no_majority_of_the_cantons() {
+        assert_eq!(
+            requires_double_majority(
+                "Decreto federale che approva e traspone nel diritto svizzero lo scambio di note \
+                 tra la Svizzera e l'UE concernente il recepimento della direttiva (UE) 2017/853"
+            ),
+            Some(false)
+        );
+        assert_eq!(
+            requires_double_majority("Decreto federale sulla sicurezza alimentare"),
+            Some(true)
+        );
+        assert_eq!(
+            requires_double_majority("Iniziativa popolare «Per un'immigrazione moderata»"),
+            Some(true)
+        );
+        assert_eq!(
+            requires_double_majority("Legge federale sulla caccia"),
+            Some(false)
+        );
+        assert_eq!(requires_double_majority("Oggetto sconosciuto"), None);
     }
-    None
 }
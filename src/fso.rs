@@ -0,0 +1,492 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use log::{info, warn};
+use polars::df;
+use polars::frame::DataFrame;
+use polars::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::constants::{
+    BALLOTS_RETURNED, CANTON, CANTON_NUMBER, COUNTED, DISTRICT, DISTRICT_NUMBER,
+    DISTRICT_RESULTS_FILE, MUNICIPALITY, MUNICIPALITY_NUMBER, MUNICIPALITY_RESULTS_FILE, NO,
+    PARTICIPATION, RATIO_YES, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALID_VOTING_BALLOTS,
+};
+use crate::data::{decimals_to_strings, save_dataframe, Data};
+use crate::matching::match_voting;
+use crate::reconcile::{ReferenceVoting, SourceError};
+
+// Results of a ballot date as published by the Federal Statistical Office on opendata.swiss,
+// one file per ballot date. Only the fields used here are listed.
+#[derive(Deserialize)]
+struct BallotDate {
+    abstimmtag: String,
+    schweiz: Switzerland,
+}
+
+#[derive(Deserialize)]
+struct Switzerland {
+    vorlagen: Vec<Proposal>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Proposal {
+    vorlagen_id: Option<u64>,
+    vorlagen_titel: Vec<Title>,
+    vorlage_angenommen: Option<bool>,
+    resultat: Option<AreaResult>,
+    staende: Option<CantonVotes>,
+    kantone: Vec<Canton>,
+}
+
+// Cantons with a whole vote and former half-cantons with half a vote
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CantonVotes {
+    ja_staende_ganz: Option<u32>,
+    ja_staende_halb: Option<u32>,
+    nein_staende_ganz: Option<u32>,
+    nein_staende_halb: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Title {
+    lang_key: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Canton {
+    geo_levelnummer: String,
+    geo_levelname: String,
+    resultat: Option<AreaResult>,
+    #[serde(default)]
+    bezirke: Vec<Area>,
+    #[serde(default)]
+    gemeinden: Vec<Area>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Area {
+    geo_levelnummer: String,
+    geo_levelname: String,
+    geo_level_parentnummer: Option<String>,
+    resultat: AreaResult,
+}
+
+// Percentages are published in percent, they are read as fractions like in the dataset
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AreaResult {
+    gebiet_ausgezaehlt: Option<bool>,
+    anzahl_stimmberechtigte: Option<u32>,
+    eingelegte_stimmzettel: Option<u32>,
+    gueltige_stimmen: Option<u32>,
+    ja_stimmen_absolut: Option<u32>,
+    nein_stimmen_absolut: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_percentage")]
+    ja_stimmen_in_prozent: Option<Decimal>,
+    #[serde(default, deserialize_with = "deserialize_percentage")]
+    stimmbeteiligung_in_prozent: Option<Decimal>,
+}
+
+// The number is read from its text, as a float would add digits that were not published
+fn deserialize_percentage<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Decimal>, D::Error> {
+    let Some(number) = Option::<serde_json::Number>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let text = number.to_string();
+    let percentage = Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .map_err(|_| serde::de::Error::custom(format!("invalid percentage {}", text)))?;
+    Ok(Some(percentage / Decimal::ONE_HUNDRED))
+}
+
+// Results of the cantons, districts or municipalities of every imported voting
+#[derive(Default)]
+pub struct AreaResults {
+    pub no: Vec<u32>,
+    pub canton_number: Vec<String>,
+    pub canton: Vec<String>,
+    pub district_number: Vec<Option<String>>,
+    pub number: Vec<String>,
+    pub name: Vec<String>,
+    pub counted: Vec<Option<bool>>,
+    pub total_voters: Vec<Option<u32>>,
+    pub ballots_returned: Vec<Option<u32>>,
+    pub valid_voting_ballots: Vec<Option<u32>>,
+    pub total_yes: Vec<Option<u32>>,
+    pub total_no: Vec<Option<u32>>,
+    pub ratio_yes: Vec<Option<Decimal>>,
+    pub participation: Vec<Option<Decimal>>,
+}
+
+impl AreaResults {
+    fn update(&mut self, no: u32, canton: &Canton, area: &Area) {
+        self.push(
+            no,
+            canton,
+            area.geo_level_parentnummer.clone(),
+            (&area.geo_levelnummer, &area.geo_levelname),
+            &area.resultat,
+        );
+    }
+
+    // A canton is the area itself, without a district
+    fn update_canton(&mut self, no: u32, canton: &Canton, result: &AreaResult) {
+        self.push(
+            no,
+            canton,
+            None,
+            (&canton.geo_levelnummer, &canton.geo_levelname),
+            result,
+        );
+    }
+
+    fn push(
+        &mut self,
+        no: u32,
+        canton: &Canton,
+        district_number: Option<String>,
+        (number, name): (&str, &str),
+        result: &AreaResult,
+    ) {
+        self.no.push(no);
+        self.canton_number.push(canton.geo_levelnummer.clone());
+        self.canton.push(canton.geo_levelname.clone());
+        self.district_number.push(district_number);
+        self.number.push(number.to_string());
+        self.name.push(name.to_string());
+        self.counted.push(result.gebiet_ausgezaehlt);
+        self.total_voters.push(result.anzahl_stimmberechtigte);
+        self.ballots_returned.push(result.eingelegte_stimmzettel);
+        self.valid_voting_ballots.push(result.gueltige_stimmen);
+        self.total_yes.push(result.ja_stimmen_absolut);
+        self.total_no.push(result.nein_stimmen_absolut);
+        self.ratio_yes.push(result.ja_stimmen_in_prozent);
+        self.participation.push(result.stimmbeteiligung_in_prozent);
+    }
+}
+
+#[derive(Default)]
+pub struct FsoResults {
+    pub districts: AreaResults,
+    pub municipalities: AreaResults,
+}
+
+fn read_ballot_dates(
+    directory: &str,
+) -> Result<Vec<(PathBuf, NaiveDate, BallotDate)>, SourceError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|error| SourceError::Io {
+            path: directory.to_string(),
+            message: error.to_string(),
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    let mut ballot_dates = Vec::new();
+    for path in paths {
+        let ballot_date = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|content| {
+                serde_json::from_str::<BallotDate>(&content).map_err(|error| error.to_string())
+            });
+        let ballot_date = match ballot_date {
+            Ok(ballot_date) => ballot_date,
+            Err(error) => {
+                warn!("Ignored {}: {}", path.display(), error);
+                continue;
+            }
+        };
+        let Ok(date) = NaiveDate::parse_from_str(&ballot_date.abstimmtag, "%Y%m%d") else {
+            warn!(
+                "Ignored {}: malformed ballot date '{}'",
+                path.display(),
+                ballot_date.abstimmtag
+            );
+            continue;
+        };
+        ballot_dates.push((path, date, ballot_date));
+    }
+    Ok(ballot_dates)
+}
+
+fn titles_of(proposal: &Proposal) -> Vec<(String, String)> {
+    proposal
+        .vorlagen_titel
+        .iter()
+        .map(|title| (title.lang_key.clone(), title.text.clone()))
+        .collect()
+}
+
+// Proposals of the files matched to the number of a voting of the dataset
+fn matched_proposals(directory: &str, data: &Data) -> Result<Vec<(u32, Proposal)>, SourceError> {
+    let mut proposals = Vec::new();
+    for (path, date, ballot_date) in read_ballot_dates(directory)? {
+        for proposal in ballot_date.schweiz.vorlagen {
+            let titles = titles_of(&proposal);
+            let Some(no) = match_voting(data, date, &titles).and_then(|idx| data.no[idx]) else {
+                warn!(
+                    "No voting or several votings of {} match '{}' of {}",
+                    date,
+                    titles
+                        .first()
+                        .map(|(_, title)| title.as_str())
+                        .unwrap_or_default(),
+                    path.display()
+                );
+                continue;
+            };
+            proposals.push((no, proposal));
+        }
+    }
+    Ok(proposals)
+}
+
+pub fn import_fso_results(directory: &str, data: &Data) -> Result<FsoResults, SourceError> {
+    let mut results = FsoResults::default();
+    for (no, proposal) in matched_proposals(directory, data)? {
+        for canton in &proposal.kantone {
+            for district in &canton.bezirke {
+                results.districts.update(no, canton, district);
+            }
+            for municipality in &canton.gemeinden {
+                results.municipalities.update(no, canton, municipality);
+            }
+        }
+    }
+    info!(
+        "Imported {} district and {} municipality results",
+        results.districts.no.len(),
+        results.municipalities.no.len()
+    );
+    Ok(results)
+}
+
+pub fn import_fso_canton_results(directory: &str, data: &Data) -> Result<AreaResults, SourceError> {
+    let mut results = AreaResults::default();
+    for (no, proposal) in matched_proposals(directory, data)? {
+        for canton in &proposal.kantone {
+            if let Some(result) = &canton.resultat {
+                results.update_canton(no, canton, result);
+            }
+        }
+    }
+    info!("Imported {} canton results", results.no.len());
+    Ok(results)
+}
+
+fn canton_votes(whole: Option<u32>, half: Option<u32>) -> Option<Decimal> {
+    Some(Decimal::from(whole?) + Decimal::from(half?) / Decimal::from(2))
+}
+
+pub fn fso_reference_votings(directory: &str) -> Result<Vec<ReferenceVoting>, SourceError> {
+    let mut votings = Vec::new();
+    for (path, date, ballot_date) in read_ballot_dates(directory)? {
+        for proposal in &ballot_date.schweiz.vorlagen {
+            let result = proposal.resultat.as_ref();
+            let cantons = proposal.staende.as_ref();
+            votings.push(ReferenceVoting {
+                source: path.display().to_string(),
+                id: proposal.vorlagen_id.map(|id| id.to_string()),
+                date,
+                titles: titles_of(proposal),
+                total_voters: result.and_then(|result| result.anzahl_stimmberechtigte),
+                ballots_returned: result.and_then(|result| result.eingelegte_stimmzettel),
+                total_yes: result.and_then(|result| result.ja_stimmen_absolut),
+                total_no: result.and_then(|result| result.nein_stimmen_absolut),
+                cantons_voting_yes: cantons.and_then(|cantons| {
+                    canton_votes(cantons.ja_staende_ganz, cantons.ja_staende_halb)
+                }),
+                cantons_voting_no: cantons.and_then(|cantons| {
+                    canton_votes(cantons.nein_staende_ganz, cantons.nein_staende_halb)
+                }),
+                accepted: proposal.vorlage_angenommen,
+            });
+        }
+    }
+    Ok(votings)
+}
+
+fn area_results_to_dataframe(
+    results: AreaResults,
+    number_column: &str,
+    name_column: &str,
+) -> DataFrame {
+    df!(
+        NO => results.no,
+        CANTON_NUMBER => results.canton_number,
+        CANTON => results.canton,
+        number_column => results.number,
+        name_column => results.name,
+        COUNTED => results.counted,
+        TOTAL_VOTERS => results.total_voters,
+        BALLOTS_RETURNED => results.ballots_returned,
+        VALID_VOTING_BALLOTS => results.valid_voting_ballots,
+        TOTAL_YES => results.total_yes,
+        TOTAL_NO => results.total_no,
+        RATIO_YES => decimals_to_strings(&results.ratio_yes),
+        PARTICIPATION => decimals_to_strings(&results.participation),
+    )
+    .unwrap()
+}
+
+pub fn save_fso_results(results: FsoResults) {
+    let mut districts = area_results_to_dataframe(results.districts, DISTRICT_NUMBER, DISTRICT);
+
+    // Municipalities are joinable to their district
+    let district_numbers = Series::new(
+        DISTRICT_NUMBER.into(),
+        results.municipalities.district_number.clone(),
+    );
+    let mut municipalities =
+        area_results_to_dataframe(results.municipalities, MUNICIPALITY_NUMBER, MUNICIPALITY);
+    municipalities.insert_column(3, district_numbers).unwrap();
+
+    for (path, df) in [
+        (DISTRICT_RESULTS_FILE, &mut districts),
+        (MUNICIPALITY_RESULTS_FILE, &mut municipalities),
+    ] {
+        save_dataframe(df, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Row;
+
+    use super::*;
+
+    const FIXTURES: &str = "tests/fixtures/fso";
+
+    fn voting(no: u32, title_de: &str) -> Row {
+        Row {
+            no: Some(no),
+            date_of_voting: NaiveDate::from_ymd_opt(2020, 11, 29),
+            title_de: title_de.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn votings_of_the_day() -> Data {
+        let mut data = Data::default();
+        // The titles of the dataset have a subtitle that the file leaves out, or the other way
+        data.update(voting(
+            640,
+            "Volksinitiative «Für verantwortungsvolle Unternehmen – zum Schutz von Mensch \
+            und Umwelt» (Konzernverantwortungsinitiative)",
+        ));
+        data.update(voting(
+            641,
+            "Volksinitiative «Für ein Verbot der Finanzierung von Kriegsmaterialproduzenten»",
+        ));
+        data
+    }
+
+    #[test]
+    fn districts_are_imported_with_their_canton() {
+        let districts = import_fso_results(FIXTURES, &votings_of_the_day())
+            .unwrap()
+            .districts;
+        assert_eq!(districts.no, vec![640, 640, 640, 641]);
+        assert_eq!(districts.canton_number, vec!["1", "1", "2", "1"]);
+        assert_eq!(districts.number, vec!["101", "102", "246", "101"]);
+        assert_eq!(districts.name[0], "Bezirk Affoltern");
+        assert_eq!(districts.canton[2], "Bern / Berne");
+        assert_eq!(districts.district_number, vec![None, None, None, None]);
+        assert_eq!(districts.total_voters[0], Some(38036));
+        assert_eq!(districts.ballots_returned[0], Some(19298));
+        assert_eq!(districts.valid_voting_ballots[0], Some(19021));
+        assert_eq!(districts.total_yes[0], Some(9106));
+        assert_eq!(districts.total_no[0], Some(9915));
+        // Percentages are read as fractions, without the digits a float would add
+        assert_eq!(districts.ratio_yes[0].unwrap().to_string(), "0.47873403");
+        assert_eq!(
+            districts.participation[0].unwrap().to_string(),
+            "0.50736145"
+        );
+    }
+
+    #[test]
+    fn municipalities_are_imported_with_their_district() {
+        let municipalities = import_fso_results(FIXTURES, &votings_of_the_day())
+            .unwrap()
+            .municipalities;
+        assert_eq!(municipalities.no, vec![640, 640, 640, 640, 641]);
+        assert_eq!(municipalities.number, vec!["1", "2", "21", "351", "1"]);
+        assert_eq!(
+            municipalities.district_number,
+            vec![
+                Some("101".to_string()),
+                Some("101".to_string()),
+                Some("102".to_string()),
+                Some("246".to_string()),
+                Some("101".to_string())
+            ]
+        );
+        assert_eq!(municipalities.name[3], "Bern");
+        assert_eq!(municipalities.counted[2], Some(false));
+        assert_eq!(municipalities.total_yes[4], Some(311));
+    }
+
+    #[test]
+    fn cantons_are_imported_from_their_results() {
+        let cantons = import_fso_canton_results(FIXTURES, &votings_of_the_day()).unwrap();
+        assert_eq!(cantons.no, vec![640, 640, 641]);
+        assert_eq!(cantons.number, vec!["1", "2", "1"]);
+        assert_eq!(cantons.total_no[1], Some(183149));
+    }
+
+    #[test]
+    fn titles_matching_several_votings_of_the_day_are_not_imported() {
+        // Both votings match the first proposal of the file equally well
+        let mut data = votings_of_the_day();
+        data.update(voting(
+            642,
+            "Volksinitiative «Für verantwortungsvolle Unternehmen – zum Schutz von Mensch \
+            und Umwelt» (Konzernverantwortungsinitiative)",
+        ));
+
+        let results = import_fso_results(FIXTURES, &data).unwrap();
+        assert_eq!(results.districts.no, vec![641]);
+        assert_eq!(results.municipalities.no, vec![641]);
+    }
+
+    #[test]
+    fn reference_votings_have_the_results_of_switzerland() {
+        let votings = fso_reference_votings(FIXTURES).unwrap();
+        assert_eq!(votings.len(), 2);
+        assert_eq!(votings[0].id.as_deref(), Some("6360"));
+        assert_eq!(
+            votings[0].date,
+            NaiveDate::from_ymd_opt(2020, 11, 29).unwrap()
+        );
+        assert_eq!(votings[0].total_yes, Some(1299173));
+        assert_eq!(votings[0].cantons_voting_yes, Some(Decimal::new(85, 1)));
+        assert_eq!(votings[0].cantons_voting_no, Some(Decimal::new(145, 1)));
+        assert_eq!(votings[0].accepted, Some(false));
+    }
+
+    #[test]
+    fn a_missing_directory_is_an_error() {
+        assert!(matches!(
+            fso_reference_votings("tests/fixtures/missing"),
+            Err(SourceError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fs;
+
+use ego_tree::NodeId;
+use scraper::{Html, Node};
+
+use crate::data::{Data, Row};
+use crate::diff::values_of;
+use crate::extractors::{fetch_html_content, parse_results_table, CellRole};
+use crate::profile::profile;
+
+pub struct CellTrace {
+    pub id: NodeId,
+    pub text: String,
+    pub role: CellRole,
+}
+
+pub fn trace_cells(document: &Html) -> Vec<CellTrace> {
+    let (_, mut roles) = parse_results_table(document);
+    let cell_selector = &profile().selectors.cell;
+    document
+        .select(cell_selector)
+        .map(|cell| CellTrace {
+            id: cell.id(),
+            text: cell.text().collect::<String>().trim().to_string(),
+            role: roles
+                .remove(&cell.id())
+                .unwrap_or(CellRole::Ignored("outside of the results table")),
+        })
+        .collect()
+}
+
+pub fn trace_to_text(row: &Row, trace: &[CellTrace]) -> String {
+    let mut lines: Vec<String> = vec![format!(
+        "Voting {}: {}",
+        row.no
+            .map_or("without number".to_string(), |no| no.to_string()),
+        row.source_url_it
+    )];
+
+    // Values are written as in the output of `diff`
+    let mut data = Data::default();
+    data.update(row.clone());
+    for (column, value) in values_of(&data, 0) {
+        lines.push(format!(
+            "  {}: {}",
+            column,
+            value.as_deref().unwrap_or("null")
+        ));
+    }
+
+    lines.push("Cells of the detail page:".to_string());
+    for cell in trace {
+        lines.push(match &cell.role {
+            CellRole::Label => format!("  label    '{}'", cell.text),
+            CellRole::Value(column) => format!("  value    '{}' -> {}", cell.text, column),
+            CellRole::UnknownLabel => format!("  unknown  '{}'", cell.text),
+            CellRole::Ignored(reason) => format!("  ignored  '{}' ({})", cell.text, reason),
+        });
+    }
+    lines.join("\n")
+}
+
+pub fn save_annotated_html(document: &Html, trace: &[CellTrace], path: &str) {
+    let styles: HashMap<NodeId, (&str, String)> = trace
+        .iter()
+        .filter_map(|cell| match &cell.role {
+            CellRole::Label => Some((cell.id, ("#ffeb9c", "label".to_string()))),
+            CellRole::Value(column) => Some((cell.id, ("#c6efce", format!("value of {}", column)))),
+            CellRole::UnknownLabel => Some((cell.id, ("#ffc7ce", "unknown label".to_string()))),
+            CellRole::Ignored(_) => None,
+        })
+        .collect();
+
+    let mut annotated = document.clone();
+    for (id, (colour, title)) in styles {
+        let mut node = annotated.tree.get_mut(id).unwrap();
+        if let Node::Element(element) = node.value() {
+            // Attribute names are built from the name of the cell, in the HTML namespace
+            let mut style = element.name.clone();
+            style.ns = "".into();
+            let mut tooltip = style.clone();
+            style.local = "style".into();
+            tooltip.local = "title".into();
+            element.attrs.insert(
+                style,
+                format!("background-color: {}; outline: 1px solid #555", colour).into(),
+            );
+            element.attrs.insert(tooltip, title.into());
+        }
+    }
+    fs::write(path, annotated.html()).expect("Could not save the annotated page");
+}
+
+pub fn inspect_page(row: &Row) -> reqwest::Result<(Html, Vec<CellTrace>)> {
+    // The page was fetched while extracting the row, hence it comes from the cache
+    let document = Html::parse_document(&fetch_html_content(&row.source_url_it)?);
+    let trace = trace_cells(&document);
+    Ok((document, trace))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::data::Tristate;
+
+    #[test]
+    fn trace_lists_the_values_and_the_cells() {
+        let document = Html::parse_document(include_str!("../tests/fixtures/det_20200927.html"));
+        let row = Row {
+            no: Some(631),
+            date_of_voting: NaiveDate::from_ymd_opt(2020, 9, 27),
+            total_voters: Some(5_479_123),
+            overseas_voters: Tristate::Value(193_460),
+            source_url_it: "https://www.bk.admin.ch/ch/i/pore/va/20200927/det631.html".to_string(),
+            ..Default::default()
+        };
+        let text = trace_to_text(&row, &trace_cells(&document));
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "Voting 631: https://www.bk.admin.ch/ch/i/pore/va/20200927/det631.html"
+        );
+        assert!(lines.contains(&"  date_of_voting: 2020-09-27"));
+        assert!(lines.contains(&"  total_voters: 5479123"));
+        assert!(lines.contains(&"  overseas_voters_status: value"));
+        assert!(lines.contains(&"  ballots_returned: null"));
+        assert!(lines.contains(&"  label    'di cui Svizzeri all'estero'"));
+        assert!(lines.contains(&"  value    '193'460' -> overseas_voters"));
+        assert!(lines.contains(&"  unknown  'Totale dei voti espressi'"));
+        // Cells of the navigation are outside of the results table
+        assert!(lines.contains(&"  ignored  'Sì' (outside of the results table)"));
+    }
+}
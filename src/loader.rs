@@ -0,0 +1,592 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::types::{FromSql, ValueRef};
+use rusqlite::Connection;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+
+use crate::constants::{
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_STATUS,
+    CANTONS_VOTING_YES, CONTENT_SHA256, DATE_OF_VOTING, DOMESTIC_VOTERS, FETCHED_AT,
+    INVALID_VOTING_BALLOTS, KIND, NO, OUTCOME, OVERSEAS_VOTERS, OVERSEAS_VOTERS_STATUS,
+    PARTICIPATION, PARTICIPATION_FRACTION, RATIO_NO, RATIO_NO_FRACTION, RATIO_YES,
+    RATIO_YES_FRACTION, RECOMMENDATION, RESULT_STATUS, SOURCE_URL_DE, SOURCE_URL_FR, SOURCE_URL_IT,
+    STATUS, SUPPLEMENTARY_URL, TITLE_DE, TITLE_FR, TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES,
+    VALIDATED_ON, VALID_VOTING_BALLOTS,
+};
+use crate::data::{Data, Row, Tristate};
+use crate::metadata::column_metadata;
+
+// Columns of the first published version of the dataset, which every dataset must contain.
+// All other known columns are either derived from these or optional.
+const REQUIRED_COLUMNS: [&str; 22] = [
+    NO,
+    DATE_OF_VOTING,
+    TITLE_IT,
+    TITLE_FR,
+    TITLE_DE,
+    KIND,
+    RECOMMENDATION,
+    TOTAL_VOTERS,
+    DOMESTIC_VOTERS,
+    OVERSEAS_VOTERS,
+    BALLOTS_RETURNED,
+    PARTICIPATION,
+    INVALID_VOTING_BALLOTS,
+    BLANK_VOTING_BALLOTS,
+    VALID_VOTING_BALLOTS,
+    TOTAL_YES,
+    RATIO_YES,
+    TOTAL_NO,
+    RATIO_NO,
+    CANTONS_VOTING_YES,
+    CANTONS_VOTING_NO,
+    OUTCOME,
+];
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(String),
+    UnsupportedFormat(String),
+    MissingColumns(Vec<String>),
+    UnknownColumns(Vec<String>),
+    InvalidValue {
+        row: usize,
+        column: String,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Io(message) => write!(f, "could not read the dataset: {}", message),
+            SchemaError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported format of '{}', expected a .csv file or a SQLite database (.db, .sqlite)",
+                path
+            ),
+            SchemaError::MissingColumns(columns) => {
+                write!(f, "missing columns: {}", columns.join(", "))
+            }
+            SchemaError::UnknownColumns(columns) => {
+                write!(f, "unknown columns: {}", columns.join(", "))
+            }
+            SchemaError::InvalidValue {
+                row,
+                column,
+                value,
+                expected,
+            } => write!(
+                f,
+                "row {}, column '{}': expected {} but found '{}'",
+                row, column, expected, value
+            ),
+        }
+    }
+}
+
+pub fn load_data(path: &str) -> Result<Data, Vec<SchemaError>> {
+    match Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("csv") => load_from_csv(path),
+        Some("db") | Some("sqlite") | Some("sqlite3") => load_from_sqlite(path),
+        _ => Err(vec![SchemaError::UnsupportedFormat(path.to_string())]),
+    }
+}
+
+pub fn load_from_csv(path: &str) -> Result<Data, Vec<SchemaError>> {
+    let mut reader =
+        csv::Reader::from_path(path).map_err(|error| vec![SchemaError::Io(error.to_string())])?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|error| vec![SchemaError::Io(error.to_string())])?
+        .iter()
+        .map(|header| header.to_string())
+        .collect();
+    check_columns(&headers)?;
+
+    // With decimal ratios the percentages are written as published and the fractions are
+    // in their own columns
+    let decimal_ratios = headers
+        .iter()
+        .any(|header| header == PARTICIPATION_FRACTION);
+
+    let mut data = Data::default();
+    let mut errors: Vec<SchemaError> = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        let record = record.map_err(|error| vec![SchemaError::Io(error.to_string())])?;
+        let mut cells = CsvCells {
+            headers: &headers,
+            record: &record,
+            row: idx + 1,
+            errors: &mut errors,
+        };
+
+        let (participation, ratio_yes, ratio_no) = match decimal_ratios {
+            true => (
+                cells.parse(PARTICIPATION_FRACTION),
+                cells.parse(RATIO_YES_FRACTION),
+                cells.parse(RATIO_NO_FRACTION),
+            ),
+            false => (
+                cells.parse(PARTICIPATION),
+                cells.parse(RATIO_YES),
+                cells.parse(RATIO_NO),
+            ),
+        };
+
+        let row = Row {
+            no: cells.parse(NO),
+            date_of_voting: cells.parse_with(DATE_OF_VOTING, parse_date),
+            title_it: cells.text(TITLE_IT),
+            title_fr: cells.text(TITLE_FR),
+            title_de: cells.text(TITLE_DE),
+            kind: cells.text(KIND),
+            recommendation: cells.optional_text(RECOMMENDATION),
+            total_voters: cells.parse(TOTAL_VOTERS),
+            domestic_voters: cells.parse(DOMESTIC_VOTERS),
+            overseas_voters: Tristate::from_status(
+                cells.parse(OVERSEAS_VOTERS),
+                cells.cell(OVERSEAS_VOTERS_STATUS),
+            ),
+            ballots_returned: cells.parse(BALLOTS_RETURNED),
+            participation,
+            invalid_voting_ballots: cells.parse(INVALID_VOTING_BALLOTS),
+            blank_voting_ballots: cells.parse(BLANK_VOTING_BALLOTS),
+            valid_voting_ballots: cells.parse(VALID_VOTING_BALLOTS),
+            total_yes: cells.parse(TOTAL_YES),
+            ratio_yes,
+            total_no: cells.parse(TOTAL_NO),
+            ratio_no,
+            cantons_voting_yes: Tristate::from_status(
+                cells.parse(CANTONS_VOTING_YES),
+                cells.cell(CANTONS_VOTING_STATUS),
+            ),
+            cantons_voting_no: Tristate::from_status(
+                cells.parse(CANTONS_VOTING_NO),
+                cells.cell(CANTONS_VOTING_STATUS),
+            ),
+            outcome: cells.parse(OUTCOME),
+            // Datasets written before scheduled votings were scraped only contain results
+            status: cells.parse(STATUS).unwrap_or_default(),
+            result_status: cells.parse(RESULT_STATUS),
+            validated_on: cells.parse_with(VALIDATED_ON, parse_date),
+            source_url_it: cells.text(SOURCE_URL_IT),
+            source_url_de: cells.text(SOURCE_URL_DE),
+            source_url_fr: cells.text(SOURCE_URL_FR),
+            supplementary_url: cells.optional_text(SUPPLEMENTARY_URL),
+            fetched_at: cells.parse_with(FETCHED_AT, parse_timestamp),
+            content_sha256: cells.text(CONTENT_SHA256),
+        };
+        data.update(row);
+    }
+
+    match errors.is_empty() {
+        true => Ok(data),
+        false => Err(errors),
+    }
+}
+
+fn check_columns(headers: &[String]) -> Result<(), Vec<SchemaError>> {
+    let mut errors: Vec<SchemaError> = Vec::new();
+
+    let missing_columns: Vec<String> = REQUIRED_COLUMNS
+        .iter()
+        .filter(|column| !headers.iter().any(|header| header == *column))
+        .map(|column| column.to_string())
+        .collect();
+    if !missing_columns.is_empty() {
+        errors.push(SchemaError::MissingColumns(missing_columns));
+    }
+
+    let unknown_columns: Vec<String> = headers
+        .iter()
+        .filter(|header| column_metadata(header).is_none())
+        .cloned()
+        .collect();
+    if !unknown_columns.is_empty() {
+        errors.push(SchemaError::UnknownColumns(unknown_columns));
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+fn expected_kind(column: &str) -> &'static str {
+    if column == OUTCOME {
+        return "an outcome (e.g. accepted, not accepted)";
+    }
+    if column == STATUS {
+        return "a status (scheduled or completed)";
+    }
+    if column == RESULT_STATUS {
+        return "a result status (provisional or final)";
+    }
+    match column_metadata(column).map(|metadata| metadata.kind) {
+        Some("integer") => "an integer",
+        Some("number") => "a number",
+        Some("date") => "a date (YYYY-MM-DD)",
+        Some("datetime") => "a timestamp (RFC 3339)",
+        _ => "a text",
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+struct CsvCells<'a> {
+    headers: &'a [String],
+    record: &'a csv::StringRecord,
+    row: usize,
+    errors: &'a mut Vec<SchemaError>,
+}
+
+impl CsvCells<'_> {
+    fn cell(&self, column: &str) -> Option<&str> {
+        let position = self.headers.iter().position(|header| header == column)?;
+        self.record.get(position).filter(|value| !value.is_empty())
+    }
+
+    fn text(&self, column: &str) -> String {
+        self.cell(column).unwrap_or_default().to_string()
+    }
+
+    fn optional_text(&self, column: &str) -> Option<String> {
+        self.cell(column).map(|value| value.to_string())
+    }
+
+    fn parse<T: FromStr>(&mut self, column: &str) -> Option<T> {
+        self.parse_with(column, |value| value.parse::<T>().ok())
+    }
+
+    fn parse_with<T>(&mut self, column: &str, parser: fn(&str) -> Option<T>) -> Option<T> {
+        let value = self.cell(column)?.to_string();
+        let parsed = parser(&value);
+        if parsed.is_none() {
+            self.errors.push(SchemaError::InvalidValue {
+                row: self.row,
+                column: column.to_string(),
+                value,
+                expected: expected_kind(column),
+            });
+        }
+        parsed
+    }
+}
+
+pub fn load_from_sqlite(path: &str) -> Result<Data, Vec<SchemaError>> {
+    if !Path::new(path).exists() {
+        return Err(vec![SchemaError::Io(format!("'{}' does not exist", path))]);
+    }
+    let connection = Connection::open(path).map_err(|error| vec![sqlite_error(error)])?;
+
+    // Tables and columns as written by `save_as_sqlite`, a missing one makes the query fail
+    let mut statement = connection
+        .prepare(
+            "SELECT
+                v.no, v.date_of_voting, it.title, fr.title, de.title, v.kind,
+                r.recommendation, v.total_voters, v.domestic_voters, v.overseas_voters,
+                v.ballots_returned, v.participation, v.invalid_voting_ballots,
+                v.blank_voting_ballots, v.valid_voting_ballots, v.total_yes, v.ratio_yes,
+                v.total_no, v.ratio_no, c.cantons_voting_yes, c.cantons_voting_no, v.outcome,
+                v.source_url_it, v.source_url_de, v.source_url_fr, r.supplementary_url,
+                v.fetched_at, v.content_sha256, v.overseas_voters_status, c.status, v.status,
+                v.result_status, v.validated_on
+             FROM votings v
+             LEFT JOIN titles it ON it.no = v.no AND it.language = 'it'
+             LEFT JOIN titles fr ON fr.no = v.no AND fr.language = 'fr'
+             LEFT JOIN titles de ON de.no = v.no AND de.language = 'de'
+             LEFT JOIN canton_results c ON c.no = v.no
+             LEFT JOIN recommendations r ON r.no = v.no
+             ORDER BY v.no",
+        )
+        .map_err(|error| vec![sqlite_error(error)])?;
+    let mut rows = statement
+        .query([])
+        .map_err(|error| vec![sqlite_error(error)])?;
+
+    let mut data = Data::default();
+    let mut errors: Vec<SchemaError> = Vec::new();
+    let mut idx = 0;
+    while let Some(sqlite_row) = rows.next().map_err(|error| vec![sqlite_error(error)])? {
+        idx += 1;
+        let mut cells = SqliteCells {
+            row: sqlite_row,
+            number: idx,
+            errors: &mut errors,
+        };
+        let row = Row {
+            no: cells.get(0, NO),
+            date_of_voting: cells.parse_with(1, DATE_OF_VOTING, parse_date),
+            title_it: cells.get(2, TITLE_IT).unwrap_or_default(),
+            title_fr: cells.get(3, TITLE_FR).unwrap_or_default(),
+            title_de: cells.get(4, TITLE_DE).unwrap_or_default(),
+            kind: cells.get(5, KIND).unwrap_or_default(),
+            recommendation: cells.get(6, RECOMMENDATION),
+            total_voters: cells.get(7, TOTAL_VOTERS),
+            domestic_voters: cells.get(8, DOMESTIC_VOTERS),
+            overseas_voters: Tristate::from_status(
+                cells.get(9, OVERSEAS_VOTERS),
+                cells.get::<String>(28, OVERSEAS_VOTERS_STATUS).as_deref(),
+            ),
+            ballots_returned: cells.get(10, BALLOTS_RETURNED),
+            participation: cells.decimal(11, PARTICIPATION),
+            invalid_voting_ballots: cells.get(12, INVALID_VOTING_BALLOTS),
+            blank_voting_ballots: cells.get(13, BLANK_VOTING_BALLOTS),
+            valid_voting_ballots: cells.get(14, VALID_VOTING_BALLOTS),
+            total_yes: cells.get(15, TOTAL_YES),
+            ratio_yes: cells.decimal(16, RATIO_YES),
+            total_no: cells.get(17, TOTAL_NO),
+            ratio_no: cells.decimal(18, RATIO_NO),
+            cantons_voting_yes: Tristate::from_status(
+                cells.decimal(19, CANTONS_VOTING_YES),
+                cells.get::<String>(29, CANTONS_VOTING_STATUS).as_deref(),
+            ),
+            cantons_voting_no: Tristate::from_status(
+                cells.decimal(20, CANTONS_VOTING_NO),
+                cells.get::<String>(29, CANTONS_VOTING_STATUS).as_deref(),
+            ),
+            outcome: cells.parse_with(21, OUTCOME, |value| value.parse().ok()),
+            status: cells
+                .parse_with(30, STATUS, |value| value.parse().ok())
+                .unwrap_or_default(),
+            result_status: cells.parse_with(31, RESULT_STATUS, |value| value.parse().ok()),
+            validated_on: cells.parse_with(32, VALIDATED_ON, parse_date),
+            source_url_it: cells.get(22, SOURCE_URL_IT).unwrap_or_default(),
+            source_url_de: cells.get(23, SOURCE_URL_DE).unwrap_or_default(),
+            source_url_fr: cells.get(24, SOURCE_URL_FR).unwrap_or_default(),
+            supplementary_url: cells.get(25, SUPPLEMENTARY_URL),
+            fetched_at: cells.parse_with(26, FETCHED_AT, parse_timestamp),
+            content_sha256: cells.get(27, CONTENT_SHA256).unwrap_or_default(),
+        };
+        data.update(row);
+    }
+
+    match errors.is_empty() {
+        true => Ok(data),
+        false => Err(errors),
+    }
+}
+
+fn sqlite_error(error: rusqlite::Error) -> SchemaError {
+    SchemaError::Io(error.to_string())
+}
+
+struct SqliteCells<'a> {
+    row: &'a rusqlite::Row<'a>,
+    number: usize,
+    errors: &'a mut Vec<SchemaError>,
+}
+
+impl SqliteCells<'_> {
+    fn get<T: FromSql>(&mut self, idx: usize, column: &str) -> Option<T> {
+        match self.row.get::<_, Option<T>>(idx) {
+            Ok(value) => value,
+            Err(_) => {
+                self.invalid_value(idx, column);
+                None
+            }
+        }
+    }
+
+    fn parse_with<T>(
+        &mut self,
+        idx: usize,
+        column: &str,
+        parser: fn(&str) -> Option<T>,
+    ) -> Option<T> {
+        let parsed = parser(&self.get::<String>(idx, column)?);
+        if parsed.is_none() {
+            self.invalid_value(idx, column);
+        }
+        parsed
+    }
+
+    fn decimal(&mut self, idx: usize, column: &str) -> Option<Decimal> {
+        match self.row.get_ref(idx) {
+            Ok(ValueRef::Null) => None,
+            Ok(ValueRef::Real(value)) => Decimal::from_f64(value),
+            Ok(ValueRef::Integer(value)) => Some(Decimal::from(value)),
+            Ok(ValueRef::Text(value)) => {
+                let parsed = Decimal::from_str(&String::from_utf8_lossy(value)).ok();
+                if parsed.is_none() {
+                    self.invalid_value(idx, column);
+                }
+                parsed
+            }
+            _ => {
+                self.invalid_value(idx, column);
+                None
+            }
+        }
+    }
+
+    fn invalid_value(&mut self, idx: usize, column: &str) {
+        let value = match self.row.get_ref(idx) {
+            Ok(ValueRef::Text(value)) => String::from_utf8_lossy(value).to_string(),
+            Ok(ValueRef::Integer(value)) => value.to_string(),
+            Ok(ValueRef::Real(value)) => value.to_string(),
+            _ => String::new(),
+        };
+        self.errors.push(SchemaError::InvalidValue {
+            row: self.number,
+            column: column.to_string(),
+            value,
+            expected: expected_kind(column),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::data::{create_dataframe_from, save_dataframe, Outcome, ResultStatus, VotingStatus};
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.csv", name, std::process::id()))
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                no: Some(640),
+                date_of_voting: NaiveDate::from_ymd_opt(2020, 9, 27),
+                title_it: "Iniziativa popolare «Per un'immigrazione moderata»".to_string(),
+                title_fr: "Initiative populaire «Pour une immigration modérée»".to_string(),
+                title_de: "Volksinitiative «Für eine massvolle Zuwanderung»".to_string(),
+                kind: "initiative".to_string(),
+                recommendation: Some("reject".to_string()),
+                total_voters: Some(5_479_123),
+                domestic_voters: Some(5_285_663),
+                overseas_voters: Tristate::Value(193_460),
+                ballots_returned: Some(3_238_412),
+                participation: Some(Decimal::new(5910, 4)),
+                invalid_voting_ballots: Some(2_717),
+                blank_voting_ballots: Some(28_118),
+                valid_voting_ballots: Some(3_207_577),
+                total_yes: Some(1_233_995),
+                ratio_yes: Some(Decimal::new(3847, 4)),
+                total_no: Some(1_973_582),
+                ratio_no: Some(Decimal::new(6153, 4)),
+                cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+                cantons_voting_no: Tristate::Value(Decimal::new(195, 1)),
+                outcome: Some(Outcome::Rejected),
+                status: VotingStatus::Completed,
+                result_status: Some(ResultStatus::Final),
+                validated_on: NaiveDate::from_ymd_opt(2020, 11, 10),
+                source_url_it: "https://www.bk.admin.ch/ch/i/pore/va/20200927/det640.html"
+                    .to_string(),
+                source_url_de: "https://www.bk.admin.ch/ch/d/pore/va/20200927/det640.html"
+                    .to_string(),
+                source_url_fr: "https://www.bk.admin.ch/ch/f/pore/va/20200927/det640.html"
+                    .to_string(),
+                supplementary_url: None,
+                fetched_at: Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap()),
+                content_sha256: "9f86d081884c7d659a2feaa0c55ad015".to_string(),
+            },
+            Row {
+                no: Some(212),
+                date_of_voting: NaiveDate::from_ymd_opt(1970, 6, 7),
+                title_it: "Legge".to_string(),
+                title_fr: "Loi".to_string(),
+                title_de: "Gesetz".to_string(),
+                kind: "referendum".to_string(),
+                overseas_voters: Tristate::NotApplicable,
+                cantons_voting_yes: Tristate::NotApplicable,
+                cantons_voting_no: Tristate::NotApplicable,
+                ratio_yes: Some(Decimal::new(4600, 4)),
+                outcome: Some(Outcome::Accepted),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn round_trip(name: &str, decimal_ratios: bool) -> Vec<Row> {
+        let mut data = Data::default();
+        rows().into_iter().for_each(|row| data.update(row));
+        let mut df = create_dataframe_from(data, decimal_ratios);
+        let path = path(name);
+        save_dataframe(&mut df, path.to_str().unwrap());
+
+        let loaded = load_data(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        (0..loaded.no.len()).map(|idx| loaded.row(idx)).collect()
+    }
+
+    fn load_csv(name: &str, contents: &str) -> Vec<SchemaError> {
+        let path = path(name);
+        std::fs::write(&path, contents).unwrap();
+        let errors = load_data(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        errors
+    }
+
+    fn header() -> String {
+        REQUIRED_COLUMNS.join(",")
+    }
+
+    #[test]
+    fn a_saved_dataset_is_loaded_back() {
+        assert_eq!(round_trip("loader-round-trip", false), rows());
+    }
+
+    #[test]
+    fn a_saved_dataset_with_decimal_ratios_is_loaded_back() {
+        assert_eq!(round_trip("loader-round-trip-decimal", true), rows());
+    }
+
+    #[test]
+    fn missing_columns_are_reported() {
+        let header = header().replace(&format!(",{}", KIND), "");
+        let errors = load_csv("loader-missing", &format!("{}\n", header));
+        assert!(matches!(
+            errors.as_slice(),
+            [SchemaError::MissingColumns(columns)] if columns == &vec![KIND.to_string()]
+        ));
+    }
+
+    #[test]
+    fn unknown_columns_are_reported() {
+        let errors = load_csv("loader-unknown", &format!("{},turnout\n", header()));
+        assert!(matches!(
+            errors.as_slice(),
+            [SchemaError::UnknownColumns(columns)] if columns == &vec!["turnout".to_string()]
+        ));
+    }
+
+    #[test]
+    fn unparsable_values_are_reported_with_their_row() {
+        let mut cells = vec![""; REQUIRED_COLUMNS.len()];
+        cells[0] = "640";
+        cells[7] = "5'479'123";
+        let errors = load_csv(
+            "loader-unparsable",
+            &format!("{}\n{}\n", header(), cells.join(",")),
+        );
+        assert!(matches!(
+            errors.as_slice(),
+            [SchemaError::InvalidValue { row: 1, column, value, expected: "an integer" }]
+                if column == TOTAL_VOTERS && value == "5'479'123"
+        ));
+    }
+}
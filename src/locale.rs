@@ -0,0 +1,266 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use log::warn;
+use polars::frame::DataFrame;
+use polars::prelude::*;
+use serde::Deserialize;
+
+use crate::cli::Locale;
+use crate::constants::MEASURE;
+use crate::data::save_dataframe;
+
+const TRANSLATIONS: &str = include_str!("../locales/translations.toml");
+
+static TRANSLATION_TABLE: OnceLock<TranslationTable> = OnceLock::new();
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::De => "de",
+            Locale::Fr => "fr",
+            Locale::It => "it",
+            Locale::En => "en",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Translation {
+    de: String,
+    fr: String,
+    it: String,
+    en: Option<String>,
+}
+
+impl Translation {
+    fn get(&self, locale: Locale) -> Option<&str> {
+        match locale {
+            Locale::De => Some(&self.de),
+            Locale::Fr => Some(&self.fr),
+            Locale::It => Some(&self.it),
+            Locale::En => self.en.as_deref(),
+        }
+    }
+}
+
+// Translations of the column names, and of the values of the categorical columns by column name
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TranslationTable {
+    columns: HashMap<String, Translation>,
+    values: HashMap<String, HashMap<String, Translation>>,
+}
+
+fn translation_table() -> &'static TranslationTable {
+    TRANSLATION_TABLE
+        .get_or_init(|| toml::from_str(TRANSLATIONS).expect("Invalid built-in translations"))
+}
+
+// Names without a translation are kept in English
+fn translate<'a>(
+    translations: &'a HashMap<String, Translation>,
+    name: &'a str,
+    locale: Locale,
+    untranslated: &mut BTreeSet<String>,
+) -> &'a str {
+    match translations.get(name) {
+        Some(translation) => translation.get(locale).unwrap_or(name),
+        None => {
+            if locale != Locale::En {
+                untranslated.insert(name.to_string());
+            }
+            name
+        }
+    }
+}
+
+pub fn localise(df: &DataFrame, locale: Locale) -> DataFrame {
+    let table = translation_table();
+    let mut untranslated = BTreeSet::new();
+    let mut columns: Vec<Column> = Vec::new();
+    for column in df.get_columns() {
+        let name = column.name().as_str();
+        // Measures of the long format are named after the columns of the dataset
+        let values = match name {
+            MEASURE => Some(&table.columns),
+            _ => table.values.get(name),
+        };
+
+        let mut column = match (values, column.str()) {
+            (Some(values), Ok(strings)) => Series::new(
+                name.into(),
+                strings
+                    .into_iter()
+                    .map(|value| {
+                        value.map(|value| translate(values, value, locale, &mut untranslated))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .into_column(),
+            _ => column.clone(),
+        };
+        column.rename(translate(&table.columns, name, locale, &mut untranslated).into());
+        columns.push(column);
+    }
+
+    for name in untranslated {
+        warn!(
+            "No {} translation of '{}', kept as it is",
+            locale.as_str(),
+            name
+        );
+    }
+    DataFrame::new(columns).expect("Translated column names are not unique")
+}
+
+// The localised copy of e.g. data.csv is data_de.csv
+fn localised_path(path: &str, locale: Locale) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!(
+            "{}_{}.{}",
+            stem,
+            locale.as_str(),
+            extension.to_string_lossy()
+        ),
+        None => format!("{}_{}", stem, locale.as_str()),
+    };
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+pub fn save_localised(df: &DataFrame, path: &str, locale: Locale) {
+    save_dataframe(&mut localise(df, locale), &localised_path(path, locale));
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::df;
+
+    use super::*;
+    use crate::constants::{KIND, NO, OUTCOME, STATUS};
+    use crate::data::{Outcome, ResultStatus, Tristate, VotingStatus};
+    use crate::metadata::COLUMNS;
+    use crate::profile::profile;
+
+    fn values_of(column: &str) -> &'static HashMap<String, Translation> {
+        translation_table()
+            .values
+            .get(column)
+            .unwrap_or_else(|| panic!("no values of {}", column))
+    }
+
+    #[test]
+    fn every_column_is_translated() {
+        let columns = &translation_table().columns;
+        for metadata in COLUMNS {
+            assert!(
+                columns.contains_key(metadata.name),
+                "no translation of {}",
+                metadata.name
+            );
+        }
+    }
+
+    #[test]
+    fn every_categorical_value_is_translated() {
+        let tristates = [
+            Tristate::Value(0).status(),
+            Tristate::<u32>::NotApplicable.status(),
+            Tristate::<u32>::Missing.status(),
+        ];
+        let kinds: Vec<&str> = profile()
+            .kinds
+            .iter()
+            .map(|kind| kind.kind.as_str())
+            .collect();
+        for (column, values) in [
+            (
+                "outcome",
+                Outcome::ALL.map(|outcome| outcome.as_str()).to_vec(),
+            ),
+            (
+                "status",
+                VotingStatus::ALL.map(|status| status.as_str()).to_vec(),
+            ),
+            (
+                "result_status",
+                ResultStatus::ALL.map(|status| status.as_str()).to_vec(),
+            ),
+            ("overseas_voters_status", tristates.to_vec()),
+            ("cantons_voting_status", tristates.to_vec()),
+            ("kind", kinds),
+        ] {
+            let translations = values_of(column);
+            for value in values {
+                assert!(
+                    translations.contains_key(value),
+                    "no translation of '{}' of {}",
+                    value,
+                    column
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn columns_and_values_are_localised() {
+        let df = df!(
+            NO => [631, 632],
+            KIND => ["initiative", "unknown"],
+            OUTCOME => [Some("not accepted"), None],
+            STATUS => ["completed", "scheduled"],
+        )
+        .unwrap();
+        let localised = localise(&df, Locale::De);
+        let names: Vec<&str> = localised
+            .get_column_names()
+            .into_iter()
+            .map(|name| name.as_str())
+            .collect();
+        assert_eq!(names, vec!["nr", "art", "ergebnis", "status"]);
+
+        let strings = |column: &str| -> Vec<Option<String>> {
+            localised
+                .column(column)
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_iter()
+                .map(|value| value.map(str::to_string))
+                .collect()
+        };
+        // Values without a translation are kept as they are
+        assert_eq!(
+            strings("art"),
+            vec![
+                Some("volksinitiative".to_string()),
+                Some("unknown".to_string())
+            ]
+        );
+        assert_eq!(
+            strings("ergebnis"),
+            vec![Some("abgelehnt".to_string()), None]
+        );
+        assert_eq!(
+            strings("status"),
+            vec![
+                Some("durchgeführt".to_string()),
+                Some("geplant".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn localised_copies_are_named_after_the_locale() {
+        assert_eq!(localised_path("data.csv", Locale::De), "data_de.csv");
+        assert_eq!(
+            localised_path("out/answers_long.csv", Locale::Fr),
+            "out/answers_long_fr.csv"
+        );
+        assert_eq!(localised_path("data", Locale::It), "data_it");
+    }
+}
@@ -1,29 +1,181 @@
 extern crate core;
 
-use std::time::Instant;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use log::info;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use log::{error, info, warn};
 
+use checkpoint::Checkpoint;
+use cli::{Cli, Command, LogFormat, OutputFormat};
+use completeness::{completeness_of, completeness_to_json, completeness_to_text};
+use constants::{CHECKPOINT_FILE, DATASET_FILE};
+use crosswalk::{add_crosswalk_columns, build_crosswalk, save_crosswalk};
+use data::Data;
 use data::{create_dataframe_from, save_as_csv};
-use extractors::execute_extractions_of_data;
+use diff::{diff_datasets, diff_to_json, diff_to_text};
+use extractors::{execute_extractions_of_data, extract_scheduled_votings, extract_voting};
+use fso::{fso_reference_votings, import_fso_canton_results, import_fso_results, save_fso_results};
+use inspect::{inspect_page, save_annotated_html, trace_to_text};
+use loader::load_data;
+use locale::save_localised;
+use manifest::save_manifest;
+use metadata::save_metadata;
+use postgresql::save_to_postgres;
+use profile::load_profile;
+use reconcile::{reconcile, reconciliation_to_json, reconciliation_to_text, SourceError};
+use sqlite::save_as_sqlite;
+use statistics::save_run_summary;
+use swissvotes::swissvotes_reference_votings;
+use tidy::save_long_format;
+use tracing_subscriber::EnvFilter;
+use watch::{drop_past_scheduled_votings, merge_scheduled_votings, notify, poll_votings, Changes};
 
+mod checkpoint;
+mod cli;
+mod completeness;
 mod constants;
 mod converters;
+mod crosswalk;
 mod data;
+mod diff;
+mod eras;
 mod extractors;
+mod fso;
+mod inspect;
+mod loader;
+mod locale;
+mod manifest;
+mod matching;
+mod metadata;
+mod postgresql;
+mod profile;
+mod reconcile;
+mod sqlite;
+mod statistics;
+mod swissvotes;
+mod tidy;
+mod watch;
 
 fn main() {
-    env_logger::init();
+    let cli = Cli::parse();
+    init_logging(cli.log_format);
+
+    if let Err(error) = load_profile(cli.profile.as_deref()) {
+        eprintln!("Could not load the scraping profile: {}", error);
+        std::process::exit(1);
+    }
+
+    match &cli.command {
+        None => create_dataset(&cli),
+        Some(Command::Check { path }) => check_dataset(path),
+        Some(Command::Diff {
+            old,
+            new,
+            tolerance,
+            format,
+        }) => compare_datasets(old, new, *tolerance, *format),
+        Some(Command::Watch {
+            interval,
+            recheck_days,
+            on_change,
+            webhook,
+        }) => watch_votings(
+            &cli,
+            *interval,
+            *recheck_days,
+            on_change.as_deref(),
+            webhook.as_deref(),
+        ),
+        Some(Command::Inspect {
+            voting,
+            annotated_html,
+        }) => inspect_voting(voting, annotated_html.as_deref()),
+        Some(Command::Completeness { path, format }) => report_completeness(path, *format),
+        Some(Command::ImportFso { directory, dataset }) => import_fso(directory, dataset),
+        Some(Command::Reconcile {
+            dataset,
+            fso,
+            swissvotes,
+            format,
+        }) => reconcile_dataset(dataset, fso.as_deref(), swissvotes.as_deref(), *format),
+    }
+}
+
+fn init_logging(format: LogFormat) {
+    // Events of the `log` macros are forwarded to the subscriber by its `tracing-log` bridge
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+fn create_dataset(cli: &Cli) {
+    // Starting over would overwrite the votings of a run that did not complete
+    if Checkpoint::exists() && !cli.resume {
+        eprintln!(
+            "'{}' of a run that did not complete exists. Run again with --resume to continue \
+            that run, or remove the file to start over.",
+            CHECKPOINT_FILE
+        );
+        std::process::exit(1);
+    }
+
+    check_sources(cli);
+
     info!("Start creation of dataset 'Swiss Popular Voting'");
     let start_time = Instant::now();
+    let started_at = Utc::now();
 
     info!("Extraction of data started");
     let extraction_time = Instant::now();
-    let data = execute_extractions_of_data();
-    info!("Extraction from data completed in {:.2?}", extraction_time.elapsed());
+    let mut data = execute_extractions_of_data(cli.resume).unwrap_or_else(|error| {
+        error!(
+            "Extraction stopped, a page could not be fetched: {}. Votings parsed so far are \
+            kept in the checkpoint, run again with --resume to continue.",
+            error
+        );
+        std::process::exit(1);
+    });
+    if cli.upcoming {
+        match extract_scheduled_votings() {
+            Ok(scheduled) => {
+                let mut changes = Changes::default();
+                merge_scheduled_votings(&mut data, scheduled, &mut changes);
+                drop_past_scheduled_votings(&mut data, Utc::now().date_naive(), &mut changes);
+            }
+            Err(error) => warn!(
+                "Scheduled votings left out, a page could not be fetched: {}",
+                error
+            ),
+        }
+    }
+    info!(
+        "Extraction from data completed in {:.2?}",
+        extraction_time.elapsed()
+    );
 
+    save_dataset(cli, &data, started_at);
+    Checkpoint::remove();
+
+    info!("Program completed in {:.2?}", start_time.elapsed())
+}
+
+fn save_dataset(cli: &Cli, data: &Data, started_at: DateTime<Utc>) {
     let df_time = Instant::now();
-    let mut df = create_dataframe_from(data);
+    let crosswalk = build_crosswalk(
+        data,
+        cli.crosswalk.as_deref(),
+        cli.swissvotes.as_deref(),
+        cli.fso.as_deref(),
+    );
+    let mut df = create_dataframe_from(data.clone(), cli.decimal_ratios);
+    add_crosswalk_columns(&mut df, data, &crosswalk);
     info!(
         "Created dataframe with {} rows and {} columns from extracted data in {:.2?}",
         df.shape().0,
@@ -34,5 +186,206 @@ fn main() {
     save_as_csv(&mut df);
     info!("Dataset successfully saved");
 
-    info!("Program completed in {:.2?}", start_time.elapsed())
+    if let Some(locale) = cli.locale {
+        save_localised(&df, DATASET_FILE, locale);
+        info!("Dataset successfully saved in locale '{}'", locale.as_str());
+    }
+
+    save_crosswalk(data, &crosswalk);
+    info!("Crosswalk successfully saved");
+
+    if cli.long {
+        let cantons = cli.fso.as_deref().and_then(|directory| {
+            import_fso_canton_results(directory, data)
+                .inspect_err(|error| warn!("Canton results left out: {}", error))
+                .ok()
+        });
+        save_long_format(data, cantons.as_ref(), cli.locale);
+        info!("Results in long format successfully saved");
+    }
+
+    save_manifest(&df, started_at);
+    info!("Manifest successfully saved");
+
+    save_metadata(&df, cli.decimal_ratios);
+    info!("Data package and Kaggle metadata successfully saved");
+
+    if let Some(path) = &cli.sqlite {
+        save_as_sqlite(data, path, cli.upsert);
+        info!("Dataset successfully saved in SQLite database '{}'", path);
+    }
+
+    if let Some(connection_string) = &cli.postgres {
+        save_to_postgres(data, connection_string, started_at);
+        info!("Dataset successfully loaded into PostgreSQL");
+    }
+
+    save_run_summary(&df, started_at);
+    info!("Run summary successfully saved");
+}
+
+fn watch_votings(
+    cli: &Cli,
+    interval: u64,
+    recheck_days: i64,
+    on_change: Option<&str>,
+    webhook: Option<&str>,
+) {
+    check_sources(cli);
+    let mut data = match Path::new(DATASET_FILE).exists() {
+        true => load_data_or_exit(DATASET_FILE),
+        false => Data::default(),
+    };
+    info!(
+        "Watching for new votings, {} votings in the dataset",
+        data.no.len()
+    );
+
+    loop {
+        let started_at = Utc::now();
+        // A page that cannot be fetched, e.g. while the site is down, only skips this poll
+        let changes = match poll_votings(&mut data, recheck_days, cli.upcoming) {
+            Ok(changes) => changes,
+            Err(error) => {
+                warn!(
+                    "Poll skipped, a page could not be fetched: {}. Next poll in {} seconds",
+                    error, interval
+                );
+                Changes::default()
+            }
+        };
+        if changes.is_empty() {
+            info!("No new or changed votings");
+        } else {
+            info!(
+                "{} votings added, {} votings updated, {} scheduled votings dropped",
+                changes.added.len(),
+                changes.updated.len(),
+                changes.removed
+            );
+            save_dataset(cli, &data, started_at);
+            notify(&changes, on_change, webhook);
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+fn references_or_exit<T>(references: Result<T, SourceError>) -> T {
+    references.unwrap_or_else(|error| {
+        eprintln!("Invalid source: {}", error);
+        std::process::exit(1);
+    })
+}
+
+// The sources of the crosswalk are checked before the pages are fetched, which takes long
+fn check_sources(cli: &Cli) {
+    if let Some(path) = &cli.swissvotes {
+        references_or_exit(swissvotes_reference_votings(path));
+    }
+    if let Some(directory) = &cli.fso {
+        references_or_exit(fso_reference_votings(directory));
+    }
+}
+
+fn load_data_or_exit(path: &str) -> Data {
+    match load_data(path) {
+        Ok(data) => data,
+        Err(errors) => {
+            eprintln!("'{}' is not a valid dataset:", path);
+            for error in errors {
+                eprintln!("  - {}", error);
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+fn check_dataset(path: &str) {
+    let data = load_data_or_exit(path);
+    println!(
+        "'{}' is a valid dataset with {} votings",
+        path,
+        data.no.len()
+    );
+}
+
+fn compare_datasets(old: &str, new: &str, tolerance: f64, format: OutputFormat) {
+    let diff = diff_datasets(&load_data_or_exit(old), &load_data_or_exit(new), tolerance);
+    match format {
+        OutputFormat::Text => println!("{}", diff_to_text(&diff)),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&diff_to_json(&diff)).unwrap()
+        ),
+    }
+}
+
+fn report_completeness(path: &str, format: OutputFormat) {
+    let report = completeness_of(&load_data_or_exit(path));
+    match format {
+        OutputFormat::Text => println!("{}", completeness_to_text(&report)),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&completeness_to_json(&report)).unwrap()
+        ),
+    }
+}
+
+fn import_fso(directory: &str, dataset: &str) {
+    let results = import_fso_results(directory, &load_data_or_exit(dataset));
+    save_fso_results(references_or_exit(results));
+    info!("Results per district and municipality successfully saved");
+}
+
+fn reconcile_dataset(
+    dataset: &str,
+    fso: Option<&str>,
+    swissvotes: Option<&str>,
+    format: OutputFormat,
+) {
+    let data = load_data_or_exit(dataset);
+    // One of both sources is required by the command line
+    let references = match (fso, swissvotes) {
+        (Some(directory), _) => fso_reference_votings(directory),
+        (None, Some(path)) => swissvotes_reference_votings(path),
+        (None, None) => unreachable!(),
+    };
+    let references = references_or_exit(references);
+
+    let reconciliation = reconcile(&data, &references);
+    match format {
+        OutputFormat::Text => println!("{}", reconciliation_to_text(&reconciliation)),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&reconciliation_to_json(&reconciliation)).unwrap()
+        ),
+    }
+    if !reconciliation.discrepancies.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn inspect_voting(voting: &str, annotated_html: Option<&str>) {
+    let fetch_failed = |error: reqwest::Error| -> ! {
+        eprintln!(
+            "Could not fetch the pages of voting '{}': {}",
+            voting, error
+        );
+        std::process::exit(1);
+    };
+    let row = match extract_voting(voting) {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            eprintln!("Voting '{}' not found on the summary page", voting);
+            std::process::exit(1);
+        }
+        Err(error) => fetch_failed(error),
+    };
+    let (document, trace) = inspect_page(&row).unwrap_or_else(|error| fetch_failed(error));
+    println!("{}", trace_to_text(&row, &trace));
+
+    if let Some(path) = annotated_html {
+        save_annotated_html(&document, &trace, path);
+        println!("Annotated page saved in '{}'", path);
+    }
 }
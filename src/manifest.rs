@@ -0,0 +1,38 @@
+use std::fs::File;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use polars::frame::DataFrame;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::constants::{DATASET_FILE, MANIFEST_FILE};
+use crate::profile::profile;
+
+pub fn save_manifest(df: &DataFrame, started_at: DateTime<Utc>) {
+    let completed_at = Utc::now();
+
+    // The hash allows to check that a published file is the one described by the manifest
+    let dataset = std::fs::read(DATASET_FILE).expect("Could not read the saved dataset");
+    let dataset_sha256 = format!("{:x}", Sha256::digest(&dataset));
+
+    let urls = &profile().urls;
+    let manifest = json!({
+        "dataset": DATASET_FILE,
+        "dataset_sha256": dataset_sha256,
+        "scraper": env!("CARGO_PKG_NAME"),
+        "scraper_version": env!("CARGO_PKG_VERSION"),
+        "started_at": started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "completed_at": completed_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "run_time_seconds": (completed_at - started_at).num_seconds(),
+        "rows": df.height(),
+        "columns": df
+            .get_column_names()
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>(),
+        "sources": [&urls.summary_page_it, &urls.summary_page_de, &urls.summary_page_fr],
+    });
+
+    let file = File::create(MANIFEST_FILE).expect("could not create file");
+    serde_json::to_writer_pretty(file, &manifest).expect("Could not save the manifest");
+}
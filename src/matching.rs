@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+
+use crate::data::Data;
+
+// Share of the words of a title that must be found in the other one for the votings to match
+const MINIMUM_TITLE_OVERLAP: f64 = 0.8;
+
+fn words_of(title: &str) -> HashSet<String> {
+    // Quotes, dashes and line breaks differ between the sources
+    title
+        .to_lowercase()
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect()
+}
+
+// The shorter title is compared with the longer one, as other sources often leave out the date
+// of an initiative or its subtitle, and the union breaks the tie between a title and a longer
+// one containing it
+fn similarity(ours: &HashSet<String>, theirs: &HashSet<String>) -> (f64, f64) {
+    let common = ours.intersection(theirs).count() as f64;
+    let shorter = ours.len().min(theirs.len()).max(1) as f64;
+    let union = ours.union(theirs).count().max(1) as f64;
+    (common / shorter, common / union)
+}
+
+// Votings of another source are aligned with the dataset on their date and title, as the
+// sources do not share an identifier. Titles are given with their language, "it", "de" or "fr",
+// other languages are ignored.
+pub fn match_voting(data: &Data, date: NaiveDate, titles: &[(String, String)]) -> Option<usize> {
+    let mut scores: Vec<((f64, f64), usize)> = (0..data.no.len())
+        .filter(|idx| data.date_of_voting[*idx] == Some(date))
+        .filter_map(|idx| {
+            titles
+                .iter()
+                .filter_map(|(language, title)| {
+                    let ours = match language.as_str() {
+                        "it" => &data.title_it[idx],
+                        "de" => &data.title_de[idx],
+                        "fr" => &data.title_fr[idx],
+                        _ => return None,
+                    };
+                    Some(similarity(&words_of(ours), &words_of(title)))
+                })
+                .max_by(|left, right| left.partial_cmp(right).unwrap())
+                .map(|score| (score, idx))
+        })
+        .filter(|((overlap, _), _)| *overlap >= MINIMUM_TITLE_OVERLAP)
+        .collect();
+    scores.sort_by(|left, right| right.0.partial_cmp(&left.0).unwrap());
+
+    // Two votings of the same day that match equally well cannot be told apart
+    match scores.as_slice() {
+        [] => None,
+        [(best, _), (second, _), ..] if best == second => None,
+        [(_, idx), ..] => Some(*idx),
+    }
+}
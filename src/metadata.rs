@@ -0,0 +1,517 @@
+use std::fs::File;
+
+use log::warn;
+use polars::frame::DataFrame;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::constants::{
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_STATUS,
+    CANTONS_VOTING_YES, CONTENT_SHA256, DATAPACKAGE_FILE, DATASET_FILE, DATASET_TITLE,
+    DATE_OF_VOTING, DECADE, DOMESTIC_VOTERS, ELECTORATE, FETCHED_AT, FSO_ID,
+    INVALID_VOTING_BALLOTS, KAGGLE_DATASET_ID, KAGGLE_METADATA_FILE, KIND, LEGISLATURE_PERIOD,
+    LICENSE_NAME, LICENSE_PATH, LICENSE_TITLE, NO, OUTCOME, OVERSEAS_VOTERS,
+    OVERSEAS_VOTERS_STATUS, PARTICIPATION, PARTICIPATION_FRACTION, QUARTER, RATIO_NO,
+    RATIO_NO_FRACTION, RATIO_YES, RATIO_YES_FRACTION, RECOMMENDATION, RESULT_STATUS, SOURCE_URL_DE,
+    SOURCE_URL_FR, SOURCE_URL_IT, STATUS, SUPPLEMENTARY_URL, SWISSVOTES_ANR, TITLE_DE, TITLE_FR,
+    TITLE_IT, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALIDATED_ON, VALID_VOTING_BALLOTS, WEEKDAY, YEAR,
+};
+use crate::profile::profile;
+
+pub struct ColumnMetadata {
+    pub name: &'static str,
+    // Field type as defined by the Frictionless Table Schema
+    pub kind: &'static str,
+    pub unit: Option<&'static str>,
+    pub description_en: &'static str,
+    pub description_de: &'static str,
+    pub description_fr: &'static str,
+    pub description_it: &'static str,
+}
+
+pub const COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata {
+        name: NO,
+        kind: "integer",
+        unit: None,
+        description_en: "Number of the voting assigned by the Federal Chancellery",
+        description_de: "Von der Bundeskanzlei vergebene Nummer der Vorlage",
+        description_fr: "Numéro de l'objet attribué par la Chancellerie fédérale",
+        description_it: "Numero dell'oggetto assegnato dalla Cancelleria federale",
+    },
+    ColumnMetadata {
+        name: DATE_OF_VOTING,
+        kind: "date",
+        unit: None,
+        description_en: "Date of the voting (ISO-8601)",
+        description_de: "Datum der Abstimmung (ISO-8601)",
+        description_fr: "Date de la votation (ISO-8601)",
+        description_it: "Data della votazione (ISO-8601)",
+    },
+    ColumnMetadata {
+        name: YEAR,
+        kind: "integer",
+        unit: Some("year"),
+        description_en: "Year of the voting",
+        description_de: "Jahr der Abstimmung",
+        description_fr: "Année de la votation",
+        description_it: "Anno della votazione",
+    },
+    ColumnMetadata {
+        name: DECADE,
+        kind: "integer",
+        unit: Some("year"),
+        description_en: "First year of the decade of the voting",
+        description_de: "Erstes Jahr des Jahrzehnts der Abstimmung",
+        description_fr: "Première année de la décennie de la votation",
+        description_it: "Primo anno del decennio della votazione",
+    },
+    ColumnMetadata {
+        name: QUARTER,
+        kind: "integer",
+        unit: None,
+        description_en: "Quarter of the year of the voting (1 to 4)",
+        description_de: "Quartal der Abstimmung (1 bis 4)",
+        description_fr: "Trimestre de la votation (1 à 4)",
+        description_it: "Trimestre della votazione (da 1 a 4)",
+    },
+    ColumnMetadata {
+        name: WEEKDAY,
+        kind: "string",
+        unit: None,
+        description_en: "Day of the week of the voting",
+        description_de: "Wochentag der Abstimmung",
+        description_fr: "Jour de la semaine de la votation",
+        description_it: "Giorno della settimana della votazione",
+    },
+    ColumnMetadata {
+        name: LEGISLATURE_PERIOD,
+        kind: "integer",
+        unit: None,
+        description_en: "Number of the legislature period of the Federal Assembly",
+        description_de: "Nummer der Legislaturperiode der Bundesversammlung",
+        description_fr: "Numéro de la législature de l'Assemblée fédérale",
+        description_it: "Numero della legislatura dell'Assemblea federale",
+    },
+    ColumnMetadata {
+        name: ELECTORATE,
+        kind: "string",
+        unit: None,
+        description_en: "Persons entitled to vote: men only before the introduction of women's suffrage in 1971",
+        description_de: "Stimmberechtigte: nur Männer vor der Einführung des Frauenstimmrechts 1971",
+        description_fr: "Électeurs : uniquement les hommes avant l'introduction du suffrage féminin en 1971",
+        description_it: "Aventi diritto di voto: solo uomini prima dell'introduzione del suffragio femminile nel 1971",
+    },
+    ColumnMetadata {
+        name: TITLE_IT,
+        kind: "string",
+        unit: None,
+        description_en: "Title of the voting in Italian",
+        description_de: "Titel der Vorlage auf Italienisch",
+        description_fr: "Titre de l'objet en italien",
+        description_it: "Titolo dell'oggetto in italiano",
+    },
+    ColumnMetadata {
+        name: TITLE_FR,
+        kind: "string",
+        unit: None,
+        description_en: "Title of the voting in French",
+        description_de: "Titel der Vorlage auf Französisch",
+        description_fr: "Titre de l'objet en français",
+        description_it: "Titolo dell'oggetto in francese",
+    },
+    ColumnMetadata {
+        name: TITLE_DE,
+        kind: "string",
+        unit: None,
+        description_en: "Title of the voting in German",
+        description_de: "Titel der Vorlage auf Deutsch",
+        description_fr: "Titre de l'objet en allemand",
+        description_it: "Titolo dell'oggetto in tedesco",
+    },
+    ColumnMetadata {
+        name: KIND,
+        kind: "string",
+        unit: None,
+        description_en: "Kind of the voting (initiative, decree, referendum, counterproposal)",
+        description_de: "Art der Vorlage (Initiative, Bundesbeschluss, Referendum, Gegenentwurf)",
+        description_fr: "Type d'objet (initiative, arrêté, référendum, contre-projet)",
+        description_it: "Tipo di oggetto (iniziativa, decreto, referendum, controprogetto)",
+    },
+    ColumnMetadata {
+        name: RECOMMENDATION,
+        kind: "string",
+        unit: None,
+        description_en: "Recommendation of the Federal Assembly on a popular initiative",
+        description_de: "Empfehlung der Bundesversammlung zu einer Volksinitiative",
+        description_fr: "Recommandation de l'Assemblée fédérale sur une initiative populaire",
+        description_it: "Raccomandazione dell'Assemblea federale su un'iniziativa popolare",
+    },
+    ColumnMetadata {
+        name: TOTAL_VOTERS,
+        kind: "integer",
+        unit: Some("voters"),
+        description_en: "Number of persons entitled to vote",
+        description_de: "Anzahl Stimmberechtigte",
+        description_fr: "Nombre d'électeurs inscrits",
+        description_it: "Numero di aventi diritto di voto",
+    },
+    ColumnMetadata {
+        name: DOMESTIC_VOTERS,
+        kind: "integer",
+        unit: Some("voters"),
+        description_en: "Number of persons entitled to vote living in Switzerland",
+        description_de: "Anzahl Stimmberechtigte mit Wohnsitz in der Schweiz",
+        description_fr: "Nombre d'électeurs inscrits résidant en Suisse",
+        description_it: "Numero di aventi diritto di voto residenti in Svizzera",
+    },
+    ColumnMetadata {
+        name: OVERSEAS_VOTERS,
+        kind: "integer",
+        unit: Some("voters"),
+        description_en: "Number of persons entitled to vote living abroad",
+        description_de: "Anzahl stimmberechtigte Auslandschweizer",
+        description_fr: "Nombre d'électeurs inscrits suisses de l'étranger",
+        description_it: "Numero di aventi diritto di voto svizzeri all'estero",
+    },
+    ColumnMetadata {
+        name: OVERSEAS_VOTERS_STATUS,
+        kind: "string",
+        unit: None,
+        description_en: "Whether overseas_voters has a value, is not applicable (before 1977) or is missing",
+        description_de: "Ob overseas_voters einen Wert hat, nicht anwendbar ist (vor 1977) oder fehlt",
+        description_fr: "Indique si overseas_voters a une valeur, n'est pas applicable (avant 1977) ou manque",
+        description_it: "Indica se overseas_voters ha un valore, non è applicabile (prima del 1977) o manca",
+    },
+    ColumnMetadata {
+        name: BALLOTS_RETURNED,
+        kind: "integer",
+        unit: Some("ballots"),
+        description_en: "Number of ballots returned",
+        description_de: "Anzahl eingelegte Stimmzettel",
+        description_fr: "Nombre de bulletins rentrés",
+        description_it: "Numero di bollettini rientrati",
+    },
+    ColumnMetadata {
+        name: PARTICIPATION,
+        kind: "number",
+        unit: Some("fraction"),
+        description_en: "Share of persons entitled to vote who returned a ballot",
+        description_de: "Anteil der Stimmberechtigten, die einen Stimmzettel eingelegt haben",
+        description_fr: "Part des électeurs inscrits ayant rendu un bulletin",
+        description_it: "Quota degli aventi diritto di voto che hanno consegnato un bollettino",
+    },
+    ColumnMetadata {
+        name: PARTICIPATION_FRACTION,
+        kind: "number",
+        unit: Some("fraction"),
+        description_en: "Exact fraction of the participation",
+        description_de: "Exakter Bruchteil der Stimmbeteiligung",
+        description_fr: "Fraction exacte de la participation",
+        description_it: "Frazione esatta della partecipazione",
+    },
+    ColumnMetadata {
+        name: INVALID_VOTING_BALLOTS,
+        kind: "integer",
+        unit: Some("ballots"),
+        description_en: "Number of invalid ballots",
+        description_de: "Anzahl ungültige Stimmzettel",
+        description_fr: "Nombre de bulletins nuls",
+        description_it: "Numero di schede nulle",
+    },
+    ColumnMetadata {
+        name: BLANK_VOTING_BALLOTS,
+        kind: "integer",
+        unit: Some("ballots"),
+        description_en: "Number of blank ballots",
+        description_de: "Anzahl leere Stimmzettel",
+        description_fr: "Nombre de bulletins blancs",
+        description_it: "Numero di schede bianche",
+    },
+    ColumnMetadata {
+        name: VALID_VOTING_BALLOTS,
+        kind: "integer",
+        unit: Some("ballots"),
+        description_en: "Number of valid ballots",
+        description_de: "Anzahl gültige Stimmzettel",
+        description_fr: "Nombre de bulletins valables",
+        description_it: "Numero di schede valide",
+    },
+    ColumnMetadata {
+        name: TOTAL_YES,
+        kind: "integer",
+        unit: Some("ballots"),
+        description_en: "Number of yes votes",
+        description_de: "Anzahl Ja-Stimmen",
+        description_fr: "Nombre de oui",
+        description_it: "Numero di sì",
+    },
+    ColumnMetadata {
+        name: RATIO_YES,
+        kind: "number",
+        unit: Some("fraction"),
+        description_en: "Share of yes votes among the valid ballots",
+        description_de: "Anteil der Ja-Stimmen an den gültigen Stimmzetteln",
+        description_fr: "Part des oui parmi les bulletins valables",
+        description_it: "Quota di sì sulle schede valide",
+    },
+    ColumnMetadata {
+        name: RATIO_YES_FRACTION,
+        kind: "number",
+        unit: Some("fraction"),
+        description_en: "Exact fraction of yes votes among the valid ballots",
+        description_de: "Exakter Bruchteil der Ja-Stimmen an den gültigen Stimmzetteln",
+        description_fr: "Fraction exacte des oui parmi les bulletins valables",
+        description_it: "Frazione esatta di sì sulle schede valide",
+    },
+    ColumnMetadata {
+        name: TOTAL_NO,
+        kind: "integer",
+        unit: Some("ballots"),
+        description_en: "Number of no votes",
+        description_de: "Anzahl Nein-Stimmen",
+        description_fr: "Nombre de non",
+        description_it: "Numero di no",
+    },
+    ColumnMetadata {
+        name: RATIO_NO,
+        kind: "number",
+        unit: Some("fraction"),
+        description_en: "Share of no votes among the valid ballots",
+        description_de: "Anteil der Nein-Stimmen an den gültigen Stimmzetteln",
+        description_fr: "Part des non parmi les bulletins valables",
+        description_it: "Quota di no sulle schede valide",
+    },
+    ColumnMetadata {
+        name: RATIO_NO_FRACTION,
+        kind: "number",
+        unit: Some("fraction"),
+        description_en: "Exact fraction of no votes among the valid ballots",
+        description_de: "Exakter Bruchteil der Nein-Stimmen an den gültigen Stimmzetteln",
+        description_fr: "Fraction exacte des non parmi les bulletins valables",
+        description_it: "Frazione esatta di no sulle schede valide",
+    },
+    ColumnMetadata {
+        name: CANTONS_VOTING_YES,
+        kind: "number",
+        unit: Some("cantons"),
+        description_en: "Number of cantons accepting the voting (half-cantons count 1/2)",
+        description_de: "Anzahl annehmende Stände (Halbkantone zählen 1/2)",
+        description_fr: "Nombre de cantons acceptants (les demi-cantons comptent 1/2)",
+        description_it: "Numero di Cantoni favorevoli (i semicantoni contano 1/2)",
+    },
+    ColumnMetadata {
+        name: CANTONS_VOTING_NO,
+        kind: "number",
+        unit: Some("cantons"),
+        description_en: "Number of cantons rejecting the voting (half-cantons count 1/2)",
+        description_de: "Anzahl ablehnende Stände (Halbkantone zählen 1/2)",
+        description_fr: "Nombre de cantons rejetants (les demi-cantons comptent 1/2)",
+        description_it: "Numero di Cantoni contrari (i semicantoni contano 1/2)",
+    },
+    ColumnMetadata {
+        name: CANTONS_VOTING_STATUS,
+        kind: "string",
+        unit: None,
+        description_en: "Whether the cantonal votes have a value, are not applicable (no majority of the cantons required) or are missing",
+        description_de: "Ob die Standesstimmen einen Wert haben, nicht anwendbar sind (kein Ständemehr erforderlich) oder fehlen",
+        description_fr: "Indique si les voix des cantons ont une valeur, ne sont pas applicables (majorité des cantons non requise) ou manquent",
+        description_it: "Indica se i voti dei Cantoni hanno un valore, non sono applicabili (maggioranza dei Cantoni non richiesta) o mancano",
+    },
+    ColumnMetadata {
+        name: OUTCOME,
+        kind: "string",
+        unit: None,
+        description_en: "Outcome of the voting: accepted, not accepted, not accepted by the cantons, not accepted by the people, counter-proposal preferred or initiative preferred (tie-break question)",
+        description_de: "Ergebnis der Abstimmung: accepted (angenommen), not accepted (abgelehnt), not accepted by the cantons (am Ständemehr gescheitert), not accepted by the people (am Volksmehr gescheitert), counter-proposal preferred oder initiative preferred (Stichfrage)",
+        description_fr: "Résultat de la votation : accepted (accepté), not accepted (rejeté), not accepted by the cantons (rejeté par les cantons), not accepted by the people (rejeté par le peuple), counter-proposal preferred ou initiative preferred (question subsidiaire)",
+        description_it: "Esito della votazione: accepted (accettato), not accepted (respinto), not accepted by the cantons (respinto dai Cantoni), not accepted by the people (respinto dal popolo), counter-proposal preferred o initiative preferred (domanda risolutiva)",
+    },
+    ColumnMetadata {
+        name: STATUS,
+        kind: "string",
+        unit: None,
+        description_en: "Whether the results are published (completed) or the voting is scheduled for a future ballot date (scheduled)",
+        description_de: "Ob die Ergebnisse veröffentlicht sind (completed) oder die Abstimmung für einen künftigen Abstimmungstermin geplant ist (scheduled)",
+        description_fr: "Indique si les résultats sont publiés (completed) ou si la votation est prévue à une date future (scheduled)",
+        description_it: "Indica se i risultati sono pubblicati (completed) o se la votazione è prevista per una data futura (scheduled)",
+    },
+    ColumnMetadata {
+        name: RESULT_STATUS,
+        kind: "string",
+        unit: None,
+        description_en: "Whether the results are provisional, as published on voting day, or final, once validated by the Federal Council",
+        description_de: "Ob die Ergebnisse vorläufig sind, wie am Abstimmungstag veröffentlicht, oder endgültig, nach der Erwahrung durch den Bundesrat",
+        description_fr: "Indique si les résultats sont provisoires, tels que publiés le jour de la votation, ou définitifs, après leur validation par le Conseil fédéral",
+        description_it: "Indica se i risultati sono provvisori, come pubblicati il giorno della votazione, o definitivi, dopo la loro convalida da parte del Consiglio federale",
+    },
+    ColumnMetadata {
+        name: VALIDATED_ON,
+        kind: "date",
+        unit: None,
+        description_en: "Date on which the Federal Council validated the results, where shown on the page",
+        description_de: "Datum der Erwahrung der Ergebnisse durch den Bundesrat, sofern auf der Seite angegeben",
+        description_fr: "Date de la validation des résultats par le Conseil fédéral, si indiquée sur la page",
+        description_it: "Data della convalida dei risultati da parte del Consiglio federale, se indicata sulla pagina",
+    },
+    ColumnMetadata {
+        name: SOURCE_URL_IT,
+        kind: "string",
+        unit: None,
+        description_en: "Page of the Federal Chancellery with the results in Italian",
+        description_de: "Seite der Bundeskanzlei mit den Ergebnissen auf Italienisch",
+        description_fr: "Page de la Chancellerie fédérale avec les résultats en italien",
+        description_it: "Pagina della Cancelleria federale con i risultati in italiano",
+    },
+    ColumnMetadata {
+        name: SOURCE_URL_DE,
+        kind: "string",
+        unit: None,
+        description_en: "Page of the Federal Chancellery with the results in German",
+        description_de: "Seite der Bundeskanzlei mit den Ergebnissen auf Deutsch",
+        description_fr: "Page de la Chancellerie fédérale avec les résultats en allemand",
+        description_it: "Pagina della Cancelleria federale con i risultati in tedesco",
+    },
+    ColumnMetadata {
+        name: SOURCE_URL_FR,
+        kind: "string",
+        unit: None,
+        description_en: "Page of the Federal Chancellery with the results in French",
+        description_de: "Seite der Bundeskanzlei mit den Ergebnissen auf Französisch",
+        description_fr: "Page de la Chancellerie fédérale avec les résultats en français",
+        description_it: "Pagina della Cancelleria federale con i risultati in francese",
+    },
+    ColumnMetadata {
+        name: SUPPLEMENTARY_URL,
+        kind: "string",
+        unit: None,
+        description_en: "Page with supplementary information on the popular initiative",
+        description_de: "Seite mit zusätzlichen Informationen zur Volksinitiative",
+        description_fr: "Page avec des informations supplémentaires sur l'initiative populaire",
+        description_it: "Pagina con informazioni supplementari sull'iniziativa popolare",
+    },
+    ColumnMetadata {
+        name: FETCHED_AT,
+        kind: "datetime",
+        unit: None,
+        description_en: "Time at which the page with the results was fetched (UTC)",
+        description_de: "Zeitpunkt, zu dem die Seite mit den Ergebnissen abgerufen wurde (UTC)",
+        description_fr: "Moment où la page avec les résultats a été téléchargée (UTC)",
+        description_it: "Momento in cui la pagina con i risultati è stata scaricata (UTC)",
+    },
+    ColumnMetadata {
+        name: CONTENT_SHA256,
+        kind: "string",
+        unit: None,
+        description_en: "SHA-256 hash of the page with the results",
+        description_de: "SHA-256-Hash der Seite mit den Ergebnissen",
+        description_fr: "Empreinte SHA-256 de la page avec les résultats",
+        description_it: "Hash SHA-256 della pagina con i risultati",
+    },
+    ColumnMetadata {
+        name: SWISSVOTES_ANR,
+        kind: "string",
+        unit: None,
+        description_en: "Number (anr) of the voting in the dataset of Swissvotes",
+        description_de: "Nummer (anr) der Abstimmung im Datensatz von Swissvotes",
+        description_fr: "Numéro (anr) de la votation dans le jeu de données de Swissvotes",
+        description_it: "Numero (anr) della votazione nel set di dati di Swissvotes",
+    },
+    ColumnMetadata {
+        name: FSO_ID,
+        kind: "string",
+        unit: None,
+        description_en: "Identifier of the voting in the open data of the Federal Statistical Office",
+        description_de: "Kennung der Abstimmung in den offenen Daten des Bundesamts für Statistik",
+        description_fr: "Identifiant de la votation dans les données ouvertes de l'Office fédéral de la statistique",
+        description_it: "Identificativo della votazione nei dati aperti dell'Ufficio federale di statistica",
+    },
+];
+
+pub fn column_metadata(name: &str) -> Option<&'static ColumnMetadata> {
+    COLUMNS.iter().find(|column| column.name == name)
+}
+
+pub fn save_metadata(df: &DataFrame, decimal_ratios: bool) {
+    let dataset = std::fs::read(DATASET_FILE).expect("Could not read the saved dataset");
+    let dataset_sha256 = format!("{:x}", Sha256::digest(&dataset));
+
+    let mut frictionless_fields: Vec<Value> = Vec::new();
+    let mut kaggle_fields: Vec<Value> = Vec::new();
+    for name in df.get_column_names() {
+        let Some(column) = column_metadata(name) else {
+            warn!(
+                "Column '{}' has no metadata and is described only by its name",
+                name
+            );
+            frictionless_fields.push(json!({ "name": name.as_str() }));
+            kaggle_fields.push(json!({ "name": name.as_str() }));
+            continue;
+        };
+
+        // With decimal ratios the percentages are written as published, i.e. in percent
+        let unit =
+            match decimal_ratios && [PARTICIPATION, RATIO_YES, RATIO_NO].contains(&column.name) {
+                true => Some("percent"),
+                false => column.unit,
+            };
+
+        frictionless_fields.push(json!({
+            "name": column.name,
+            "type": column.kind,
+            "unit": unit,
+            "description": column.description_en,
+            "descriptions": {
+                "en": column.description_en,
+                "de": column.description_de,
+                "fr": column.description_fr,
+                "it": column.description_it,
+            },
+        }));
+        kaggle_fields.push(json!({
+            "name": column.name,
+            "type": column.kind,
+            "description": column.description_en,
+        }));
+    }
+
+    let urls = &profile().urls;
+    let datapackage = json!({
+        "name": KAGGLE_DATASET_ID.split('/').next_back().unwrap(),
+        "title": DATASET_TITLE,
+        "version": env!("CARGO_PKG_VERSION"),
+        "licenses": [{ "name": LICENSE_NAME, "title": LICENSE_TITLE, "path": LICENSE_PATH }],
+        "sources": [
+            { "title": "Federal Chancellery (IT)", "path": urls.summary_page_it },
+            { "title": "Federal Chancellery (DE)", "path": urls.summary_page_de },
+            { "title": "Federal Chancellery (FR)", "path": urls.summary_page_fr },
+        ],
+        "resources": [{
+            "name": "data",
+            "path": DATASET_FILE,
+            "format": "csv",
+            "mediatype": "text/csv",
+            "encoding": "utf-8",
+            "bytes": dataset.len(),
+            "hash": format!("sha256:{}", dataset_sha256),
+            "stats": { "rows": df.height(), "fields": df.width() },
+            "schema": { "fields": frictionless_fields },
+        }],
+    });
+    let file = File::create(DATAPACKAGE_FILE).expect("could not create file");
+    serde_json::to_writer_pretty(file, &datapackage).expect("Could not save the data package");
+
+    let kaggle_metadata = json!({
+        "title": DATASET_TITLE,
+        "id": KAGGLE_DATASET_ID,
+        "licenses": [{ "name": "other" }],
+        "resources": [{
+            "path": DATASET_FILE,
+            "description": format!("{} ({} votings)", DATASET_TITLE, df.height()),
+            "schema": { "fields": kaggle_fields },
+        }],
+    });
+    let file = File::create(KAGGLE_METADATA_FILE).expect("could not create file");
+    serde_json::to_writer_pretty(file, &kaggle_metadata)
+        .expect("Could not save the Kaggle metadata");
+}
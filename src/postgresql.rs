@@ -0,0 +1,530 @@
+use chrono::{DateTime, Datelike, Utc};
+use log::{info, warn};
+use postgres::types::ToSql;
+use postgres::{Client, NoTls, Transaction};
+
+use crate::converters::{
+    date_to_decade, date_to_legislature_period, date_to_quarter, date_to_weekday,
+};
+use crate::data::{cantons_voting_status, Data};
+
+// Versioned migrations of the warehouse schema. Applied migrations are recorded in the table
+// `schema_migrations`, so new ones must be appended and existing ones never modified.
+const MIGRATIONS: [(i32, &str); 5] = [
+    (
+        1,
+        "
+        CREATE TABLE voting_days (
+            date_of_voting DATE PRIMARY KEY,
+            year INTEGER NOT NULL,
+            decade INTEGER NOT NULL,
+            quarter INTEGER NOT NULL,
+            weekday TEXT NOT NULL,
+            legislature_period INTEGER
+        );
+        CREATE TABLE votings (
+            no BIGINT PRIMARY KEY,
+            date_of_voting DATE REFERENCES voting_days (date_of_voting),
+            kind TEXT NOT NULL,
+            outcome TEXT,
+            total_voters BIGINT,
+            domestic_voters BIGINT,
+            overseas_voters BIGINT,
+            ballots_returned BIGINT,
+            participation NUMERIC,
+            invalid_voting_ballots BIGINT,
+            blank_voting_ballots BIGINT,
+            valid_voting_ballots BIGINT,
+            total_yes BIGINT,
+            ratio_yes NUMERIC,
+            total_no BIGINT,
+            ratio_no NUMERIC,
+            source_url_it TEXT NOT NULL,
+            source_url_de TEXT NOT NULL,
+            source_url_fr TEXT NOT NULL,
+            fetched_at TIMESTAMPTZ,
+            content_sha256 TEXT NOT NULL
+        );
+        CREATE INDEX votings_date_of_voting ON votings (date_of_voting);
+        CREATE INDEX votings_kind ON votings (kind);
+        CREATE TABLE titles (
+            no BIGINT NOT NULL REFERENCES votings (no) ON DELETE CASCADE,
+            language TEXT NOT NULL,
+            title TEXT NOT NULL,
+            PRIMARY KEY (no, language)
+        );
+        CREATE TABLE canton_results (
+            no BIGINT PRIMARY KEY REFERENCES votings (no) ON DELETE CASCADE,
+            cantons_voting_yes NUMERIC,
+            cantons_voting_no NUMERIC
+        );
+        CREATE TABLE recommendations (
+            no BIGINT PRIMARY KEY REFERENCES votings (no) ON DELETE CASCADE,
+            recommendation TEXT NOT NULL,
+            supplementary_url TEXT
+        );
+        ",
+    ),
+    (
+        2,
+        "
+        CREATE TABLE runs (
+            id BIGSERIAL PRIMARY KEY,
+            scraper_version TEXT NOT NULL,
+            started_at TIMESTAMPTZ NOT NULL,
+            completed_at TIMESTAMPTZ,
+            rows BIGINT NOT NULL
+        );
+        ALTER TABLE votings ADD COLUMN run_id BIGINT REFERENCES runs (id);
+        ",
+    ),
+    (
+        3,
+        "
+        ALTER TABLE votings ADD COLUMN overseas_voters_status TEXT NOT NULL DEFAULT 'missing';
+        ALTER TABLE canton_results ADD COLUMN status TEXT NOT NULL DEFAULT 'missing';
+        ",
+    ),
+    (
+        4,
+        "
+        ALTER TABLE votings ADD COLUMN status TEXT NOT NULL DEFAULT 'completed';
+        ",
+    ),
+    (
+        5,
+        "
+        ALTER TABLE votings ADD COLUMN result_status TEXT;
+        ALTER TABLE votings ADD COLUMN validated_on DATE;
+        ",
+    ),
+];
+
+pub fn save_to_postgres(data: &Data, connection_string: &str, started_at: DateTime<Utc>) {
+    let mut client =
+        Client::connect(connection_string, NoTls).expect("Could not connect to PostgreSQL");
+
+    migrate(&mut client);
+
+    let mut transaction = client.transaction().expect("Could not start a transaction");
+    let run_id: i64 = transaction
+        .query_one(
+            "INSERT INTO runs (scraper_version, started_at, rows) VALUES ($1, $2, $3) RETURNING id",
+            &[
+                &env!("CARGO_PKG_VERSION"),
+                &started_at,
+                &(data.no.len() as i64),
+            ],
+        )
+        .expect("Could not record the run")
+        .get(0);
+
+    for idx in 0..data.no.len() {
+        let Some(no) = data.no[idx] else {
+            warn!(
+                "Voting {} has no number and is not written to PostgreSQL",
+                data.source_url_it[idx]
+            );
+            continue;
+        };
+        upsert_voting(&mut transaction, data, idx, i64::from(no), run_id);
+    }
+
+    transaction
+        .execute(
+            "UPDATE runs SET completed_at = $1 WHERE id = $2",
+            &[&Utc::now(), &run_id],
+        )
+        .expect("Could not record the end of the run");
+    transaction
+        .commit()
+        .expect("Could not commit to PostgreSQL");
+}
+
+fn migrate(client: &mut Client) {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .expect("Could not create the table of migrations");
+
+    let current_version: i32 = client
+        .query_one(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+            &[],
+        )
+        .expect("Could not read the schema version")
+        .get(0);
+
+    for (version, migration) in MIGRATIONS {
+        if version <= current_version {
+            continue;
+        }
+        let mut transaction = client.transaction().expect("Could not start a transaction");
+        transaction
+            .batch_execute(migration)
+            .expect("Could not apply migration");
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version) VALUES ($1)",
+                &[&version],
+            )
+            .expect("Could not record migration");
+        transaction.commit().expect("Could not commit migration");
+        info!("Applied migration {} of the PostgreSQL schema", version);
+    }
+}
+
+// Values of a row, each with the column it is written to
+type Parameters = Vec<(&'static str, Box<dyn ToSql + Sync>)>;
+
+fn upsert_voting(transaction: &mut Transaction, data: &Data, idx: usize, no: i64, run_id: i64) {
+    if let Some(parameters) = voting_day_parameters(data, idx) {
+        upsert(
+            transaction,
+            "voting_days",
+            "date_of_voting",
+            &parameters,
+            false,
+        )
+        .expect("Could not write voting day");
+    }
+
+    upsert(
+        transaction,
+        "votings",
+        "no",
+        &voting_parameters(data, idx, no, run_id),
+        true,
+    )
+    .expect("Could not write voting");
+
+    for parameters in title_parameters(data, idx, no) {
+        upsert(transaction, "titles", "no, language", &parameters, true)
+            .expect("Could not write title");
+    }
+
+    upsert(
+        transaction,
+        "canton_results",
+        "no",
+        &canton_result_parameters(data, idx, no),
+        true,
+    )
+    .expect("Could not write canton results");
+
+    // Only popular initiatives carry a recommendation of the Federal Assembly
+    if let Some(parameters) = recommendation_parameters(data, idx, no) {
+        upsert(transaction, "recommendations", "no", &parameters, true)
+            .expect("Could not write recommendation");
+    }
+}
+
+fn upsert(
+    transaction: &mut Transaction,
+    table: &str,
+    key: &str,
+    parameters: &Parameters,
+    update: bool,
+) -> Result<u64, postgres::Error> {
+    let columns: Vec<&str> = parameters.iter().map(|(column, _)| *column).collect();
+    let values: Vec<&(dyn ToSql + Sync)> =
+        parameters.iter().map(|(_, value)| value.as_ref()).collect();
+    transaction.execute(&upsert_statement(table, key, &columns, update), &values)
+}
+
+// The columns of the key are kept on conflict, all others are updated unless `update` is false
+fn upsert_statement(table: &str, key: &str, columns: &[&str], update: bool) -> String {
+    let placeholders: Vec<String> = (1..=columns.len())
+        .map(|position| format!("${}", position))
+        .collect();
+    let key_columns: Vec<&str> = key.split(", ").collect();
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|column| !key_columns.contains(column))
+        .map(|column| format!("{column} = excluded.{column}"))
+        .collect();
+    let on_conflict = match update && !updates.is_empty() {
+        true => format!("DO UPDATE SET {}", updates.join(", ")),
+        false => "DO NOTHING".to_string(),
+    };
+    format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) {}",
+        table,
+        columns.join(", "),
+        placeholders.join(", "),
+        key,
+        on_conflict
+    )
+}
+
+fn voting_day_parameters(data: &Data, idx: usize) -> Option<Parameters> {
+    let date = data.date_of_voting[idx]?;
+    Some(vec![
+        ("date_of_voting", Box::new(date)),
+        ("year", Box::new(date.year())),
+        ("decade", Box::new(date_to_decade(&date))),
+        ("quarter", Box::new(date_to_quarter(&date) as i32)),
+        ("weekday", Box::new(date_to_weekday(&date))),
+        (
+            "legislature_period",
+            Box::new(date_to_legislature_period(&date).map(|period| period as i32)),
+        ),
+    ])
+}
+
+fn voting_parameters(data: &Data, idx: usize, no: i64, run_id: i64) -> Parameters {
+    vec![
+        ("no", Box::new(no)),
+        ("date_of_voting", Box::new(data.date_of_voting[idx])),
+        ("kind", Box::new(data.kind[idx].clone())),
+        (
+            "outcome",
+            Box::new(data.outcome[idx].map(|outcome| outcome.as_str())),
+        ),
+        ("total_voters", Box::new(to_i64(data.total_voters[idx]))),
+        (
+            "domestic_voters",
+            Box::new(to_i64(data.domestic_voters[idx])),
+        ),
+        (
+            "overseas_voters",
+            Box::new(to_i64(data.overseas_voters[idx].value())),
+        ),
+        (
+            "overseas_voters_status",
+            Box::new(data.overseas_voters[idx].status()),
+        ),
+        (
+            "ballots_returned",
+            Box::new(to_i64(data.ballots_returned[idx])),
+        ),
+        ("participation", Box::new(data.participation[idx])),
+        (
+            "invalid_voting_ballots",
+            Box::new(to_i64(data.invalid_voting_ballots[idx])),
+        ),
+        (
+            "blank_voting_ballots",
+            Box::new(to_i64(data.blank_voting_ballots[idx])),
+        ),
+        (
+            "valid_voting_ballots",
+            Box::new(to_i64(data.valid_voting_ballots[idx])),
+        ),
+        ("total_yes", Box::new(to_i64(data.total_yes[idx]))),
+        ("ratio_yes", Box::new(data.ratio_yes[idx])),
+        ("total_no", Box::new(to_i64(data.total_no[idx]))),
+        ("ratio_no", Box::new(data.ratio_no[idx])),
+        ("source_url_it", Box::new(data.source_url_it[idx].clone())),
+        ("source_url_de", Box::new(data.source_url_de[idx].clone())),
+        ("source_url_fr", Box::new(data.source_url_fr[idx].clone())),
+        ("fetched_at", Box::new(data.fetched_at[idx])),
+        ("content_sha256", Box::new(data.content_sha256[idx].clone())),
+        ("run_id", Box::new(run_id)),
+        ("status", Box::new(data.status[idx].as_str())),
+        (
+            "result_status",
+            Box::new(data.result_status[idx].map(|status| status.as_str())),
+        ),
+        ("validated_on", Box::new(data.validated_on[idx])),
+    ]
+}
+
+fn title_parameters(data: &Data, idx: usize, no: i64) -> Vec<Parameters> {
+    [
+        ("it", &data.title_it[idx]),
+        ("de", &data.title_de[idx]),
+        ("fr", &data.title_fr[idx]),
+    ]
+    .into_iter()
+    .map(|(language, title)| -> Parameters {
+        vec![
+            ("no", Box::new(no)),
+            ("language", Box::new(language)),
+            ("title", Box::new(title.clone())),
+        ]
+    })
+    .collect()
+}
+
+fn canton_result_parameters(data: &Data, idx: usize, no: i64) -> Parameters {
+    vec![
+        ("no", Box::new(no)),
+        (
+            "cantons_voting_yes",
+            Box::new(data.cantons_voting_yes[idx].value()),
+        ),
+        (
+            "cantons_voting_no",
+            Box::new(data.cantons_voting_no[idx].value()),
+        ),
+        (
+            "status",
+            Box::new(cantons_voting_status(
+                data.cantons_voting_yes[idx],
+                data.cantons_voting_no[idx],
+            )),
+        ),
+    ]
+}
+
+fn recommendation_parameters(data: &Data, idx: usize, no: i64) -> Option<Parameters> {
+    let recommendation = data.recommendation[idx].clone()?;
+    Some(vec![
+        ("no", Box::new(no)),
+        ("recommendation", Box::new(recommendation)),
+        (
+            "supplementary_url",
+            Box::new(data.supplementary_url[idx].clone()),
+        ),
+    ])
+}
+
+fn to_i64(value: Option<u32>) -> Option<i64> {
+    value.map(i64::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::data::{Row, Tristate};
+
+    // Columns of every table once all migrations are applied
+    fn schema() -> HashMap<String, Vec<String>> {
+        let mut tables: HashMap<String, Vec<String>> = HashMap::new();
+        for (_, migration) in MIGRATIONS {
+            let mut table: Option<String> = None;
+            for line in migration.lines().map(str::trim) {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match words.as_slice() {
+                    ["CREATE", "TABLE", name, "("] => table = Some(name.to_string()),
+                    [");"] => table = None,
+                    ["ALTER", "TABLE", name, "ADD", "COLUMN", column, ..] => tables
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(column.to_string()),
+                    [column, ..] if !column.chars().any(char::is_uppercase) => {
+                        if let Some(table) = &table {
+                            tables
+                                .entry(table.clone())
+                                .or_default()
+                                .push(column.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        tables
+    }
+
+    fn data() -> Data {
+        let mut data = Data::default();
+        data.update(Row {
+            no: Some(640),
+            date_of_voting: NaiveDate::from_ymd_opt(2020, 9, 27),
+            title_it: "Legge".to_string(),
+            title_de: "Gesetz".to_string(),
+            title_fr: "Loi".to_string(),
+            kind: "initiative".to_string(),
+            recommendation: Some("reject".to_string()),
+            total_voters: Some(5_479_123),
+            overseas_voters: Tristate::NotApplicable,
+            ratio_yes: Some(Decimal::new(3830, 4)),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            cantons_voting_no: Tristate::Value(Decimal::new(195, 1)),
+            content_sha256: "abc".to_string(),
+            ..Default::default()
+        });
+        data
+    }
+
+    fn columns(parameters: &Parameters) -> Vec<&str> {
+        parameters.iter().map(|(column, _)| *column).collect()
+    }
+
+    fn value_of(parameters: &Parameters, column: &str) -> String {
+        let (_, value) = parameters.iter().find(|(name, _)| *name == column).unwrap();
+        format!("{:?}", value)
+    }
+
+    #[test]
+    fn migrations_are_numbered_in_order() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|(version, _)| *version).collect();
+        assert_eq!(versions, (1..=MIGRATIONS.len() as i32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn every_written_column_exists_in_the_schema() {
+        let schema = schema();
+        let data = data();
+        for (table, parameters) in [
+            ("voting_days", voting_day_parameters(&data, 0).unwrap()),
+            ("votings", voting_parameters(&data, 0, 640, 1)),
+            ("titles", title_parameters(&data, 0, 640).remove(0)),
+            ("canton_results", canton_result_parameters(&data, 0, 640)),
+            (
+                "recommendations",
+                recommendation_parameters(&data, 0, 640).unwrap(),
+            ),
+        ] {
+            let mut written = columns(&parameters);
+            let mut expected: Vec<&str> = schema[table].iter().map(String::as_str).collect();
+            written.sort();
+            expected.sort();
+            assert_eq!(written, expected, "columns of {}", table);
+        }
+    }
+
+    #[test]
+    fn values_are_given_to_their_column() {
+        let data = data();
+        let parameters = voting_parameters(&data, 0, 640, 7);
+        assert_eq!(value_of(&parameters, "no"), "640");
+        assert_eq!(value_of(&parameters, "run_id"), "7");
+        assert_eq!(value_of(&parameters, "total_voters"), "Some(5479123)");
+        assert_eq!(value_of(&parameters, "overseas_voters"), "None");
+        assert_eq!(
+            value_of(&parameters, "overseas_voters_status"),
+            "\"not_applicable\""
+        );
+        assert_eq!(value_of(&parameters, "ratio_yes"), "Some(0.3830)");
+        assert_eq!(value_of(&parameters, "content_sha256"), "\"abc\"");
+
+        let titles = title_parameters(&data, 0, 640);
+        assert_eq!(value_of(&titles[1], "language"), "\"de\"");
+        assert_eq!(value_of(&titles[1], "title"), "\"Gesetz\"");
+
+        let cantons = canton_result_parameters(&data, 0, 640);
+        assert_eq!(value_of(&cantons, "cantons_voting_no"), "Some(19.5)");
+        assert_eq!(value_of(&cantons, "status"), "\"value\"");
+    }
+
+    #[test]
+    fn statements_name_their_columns() {
+        assert_eq!(
+            upsert_statement("titles", "no, language", &["no", "language", "title"], true),
+            "INSERT INTO titles (no, language, title) VALUES ($1, $2, $3) \
+             ON CONFLICT (no, language) DO UPDATE SET title = excluded.title"
+        );
+        assert_eq!(
+            upsert_statement(
+                "voting_days",
+                "date_of_voting",
+                &["date_of_voting", "year"],
+                false
+            ),
+            "INSERT INTO voting_days (date_of_voting, year) VALUES ($1, $2) \
+             ON CONFLICT (date_of_voting) DO NOTHING"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use scraper::Selector;
+use serde::{Deserialize, Deserializer};
+
+use crate::constants::{
+    BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTONS_VOTING_NO, CANTONS_VOTING_YES,
+    INVALID_VOTING_BALLOTS, OVERSEAS_VOTERS, PARTICIPATION, RATIO_NO, RATIO_YES, TOTAL_NO,
+    TOTAL_VOTERS, TOTAL_YES, VALID_VOTING_BALLOTS,
+};
+use crate::data::Outcome;
+
+const DEFAULT_PROFILE: &str = include_str!("../profiles/default.toml");
+
+// Columns that can be filled from the results table of a detail page
+const TABLE_COLUMNS: [&str; 13] = [
+    TOTAL_VOTERS,
+    OVERSEAS_VOTERS,
+    BALLOTS_RETURNED,
+    PARTICIPATION,
+    BLANK_VOTING_BALLOTS,
+    INVALID_VOTING_BALLOTS,
+    VALID_VOTING_BALLOTS,
+    TOTAL_YES,
+    RATIO_YES,
+    TOTAL_NO,
+    RATIO_NO,
+    CANTONS_VOTING_YES,
+    CANTONS_VOTING_NO,
+];
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub urls: Urls,
+    pub selectors: Selectors,
+    pub summary_page: SummaryPage,
+    pub outcomes: Outcomes,
+    pub kinds: Vec<Kind>,
+    pub table_labels: Vec<TableLabel>,
+    pub supplementary_information: SupplementaryInformation,
+    pub result_status: ResultStatusMarkers,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Urls {
+    pub summary_page_it: String,
+    pub summary_page_de: String,
+    pub summary_page_fr: String,
+    pub detail_page_it: String,
+    pub detail_page_de: String,
+    pub detail_page_fr: String,
+    pub upcoming_page_it: String,
+    pub upcoming_page_de: String,
+    pub upcoming_page_fr: String,
+    pub site: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Selectors {
+    #[serde(deserialize_with = "deserialize_selector")]
+    pub row: Selector,
+    #[serde(deserialize_with = "deserialize_selector")]
+    pub link: Selector,
+    #[serde(deserialize_with = "deserialize_selector")]
+    pub cell: Selector,
+    #[serde(deserialize_with = "deserialize_selector")]
+    pub table: Selector,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryPage {
+    #[serde(deserialize_with = "deserialize_pattern")]
+    pub voting_number: Regex,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Outcomes {
+    pub it: OutcomeSentences,
+    pub de: OutcomeSentences,
+    pub fr: OutcomeSentences,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OutcomeSentences {
+    pub prefixes: Vec<String>,
+    pub sentences: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Kind {
+    pub keyword: String,
+    pub kind: String,
+    pub double_majority: bool,
+    // Keywords of the titles that are subject to the optional referendum despite the kind
+    #[serde(default)]
+    pub optional_referendum: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TableLabel {
+    pub label: String,
+    pub columns: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SupplementaryInformation {
+    pub link_text: String,
+    pub recommendation_label: String,
+    pub rejection_keyword: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ResultStatusMarkers {
+    pub provisional_markers: Vec<String>,
+    #[serde(deserialize_with = "deserialize_pattern")]
+    pub validation_date: Regex,
+}
+
+// Patterns and selectors are compiled when the profile is loaded, as they are used on every page
+fn deserialize_selector<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Selector, D::Error> {
+    let selector = String::deserialize(deserializer)?;
+    Selector::parse(&selector).map_err(|_| {
+        serde::de::Error::custom(ProfileError::InvalidSelector(selector.clone()).to_string())
+    })
+}
+
+fn deserialize_pattern<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(|_| {
+        serde::de::Error::custom(ProfileError::InvalidPattern(pattern.clone()).to_string())
+    })
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Syntax(String),
+    InvalidSelector(String),
+    InvalidPattern(String),
+    UnknownColumn { label: String, column: String },
+    UnknownOutcome { sentence: String, outcome: String },
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::Io(error) => write!(f, "could not read the profile: {}", error),
+            ProfileError::Syntax(error) => write!(f, "invalid profile: {}", error),
+            ProfileError::InvalidSelector(selector) => {
+                write!(f, "invalid CSS selector '{}'", selector)
+            }
+            ProfileError::InvalidPattern(pattern) => {
+                write!(f, "invalid regular expression '{}'", pattern)
+            }
+            ProfileError::UnknownColumn { label, column } => write!(
+                f,
+                "label '{}' fills the unknown column '{}', expected one of: {}",
+                label,
+                column,
+                TABLE_COLUMNS.join(", ")
+            ),
+            ProfileError::UnknownOutcome { sentence, outcome } => write!(
+                f,
+                "sentence '{}' has the unknown outcome '{}', expected one of: {}",
+                sentence,
+                outcome,
+                Outcome::ALL.map(|outcome| outcome.as_str()).join(", ")
+            ),
+        }
+    }
+}
+
+impl Profile {
+    fn validate(&self) -> Result<(), ProfileError> {
+        let outcomes = &self.outcomes;
+        for (sentence, outcome) in [&outcomes.it, &outcomes.de, &outcomes.fr]
+            .into_iter()
+            .flat_map(|sentences| &sentences.sentences)
+        {
+            if outcome.parse::<Outcome>().is_err() {
+                return Err(ProfileError::UnknownOutcome {
+                    sentence: sentence.clone(),
+                    outcome: outcome.clone(),
+                });
+            }
+        }
+
+        for table_label in &self.table_labels {
+            for column in &table_label.columns {
+                if !TABLE_COLUMNS.contains(&column.as_str()) {
+                    return Err(ProfileError::UnknownColumn {
+                        label: table_label.label.clone(),
+                        column: column.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_profile(content: &str) -> Result<Profile, ProfileError> {
+    let profile: Profile =
+        toml::from_str(content).map_err(|error| ProfileError::Syntax(error.to_string()))?;
+    profile.validate()?;
+    Ok(profile)
+}
+
+pub fn load_profile(path: Option<&str>) -> Result<(), ProfileError> {
+    let profile = match path {
+        Some(path) => parse_profile(&fs::read_to_string(path).map_err(ProfileError::Io)?)?,
+        None => parse_profile(DEFAULT_PROFILE)?,
+    };
+    PROFILE
+        .set(profile)
+        .expect("The profile can only be loaded once");
+    Ok(())
+}
+
+pub fn profile() -> &'static Profile {
+    PROFILE.get_or_init(|| parse_profile(DEFAULT_PROFILE).expect("Invalid built-in profile"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_with(from: &str, to: &str) -> Result<Profile, ProfileError> {
+        assert!(
+            DEFAULT_PROFILE.contains(from),
+            "'{}' is not in the profile",
+            from
+        );
+        parse_profile(&DEFAULT_PROFILE.replacen(from, to, 1))
+    }
+
+    fn syntax_error(result: Result<Profile, ProfileError>) -> String {
+        match result {
+            Err(ProfileError::Syntax(message)) => message,
+            other => panic!("expected a syntax error, found {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn the_built_in_profile_is_valid() {
+        let profile = parse_profile(DEFAULT_PROFILE).unwrap();
+        let captures = profile
+            .summary_page
+            .voting_number
+            .captures("va/20200927/det640.html")
+            .unwrap();
+        assert_eq!(&captures[1], "640");
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let message = syntax_error(parse_with("[urls]\n", "[urls]\nsummary_page_en = \"\"\n"));
+        assert!(
+            message.contains("unknown field `summary_page_en`"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn invalid_selectors_are_rejected() {
+        let message = syntax_error(parse_with("cell = \"td\"", "cell = \"td[\""));
+        assert!(
+            message.contains("invalid CSS selector 'td['"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let message = syntax_error(parse_with(r"'det(\d+)\.html'", r"'det(\d+\.html'"));
+        assert!(
+            message.contains(r"invalid regular expression 'det(\d+\.html'"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn unknown_outcomes_are_rejected() {
+        let result = parse_with(
+            "\"L'oggetto è stato accettato\" = \"accepted\"",
+            "\"L'oggetto è stato accettato\" = \"approved\"",
+        );
+        assert!(matches!(
+            result,
+            Err(ProfileError::UnknownOutcome { sentence, outcome })
+                if sentence == "L'oggetto è stato accettato" && outcome == "approved"
+        ));
+    }
+}
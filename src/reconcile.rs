@@ -0,0 +1,344 @@
+use std::fmt;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+
+use crate::constants::{
+    BALLOTS_RETURNED, CANTONS_VOTING_NO, CANTONS_VOTING_YES, OUTCOME, TOTAL_NO, TOTAL_VOTERS,
+    TOTAL_YES,
+};
+use crate::data::{Data, Outcome};
+use crate::matching::match_voting;
+
+// Voting of an independent source, such as the files of the Federal Statistical Office or the
+// dataset of Swissvotes. Values the source does not have are `None` and not compared.
+pub struct ReferenceVoting {
+    // File, and line where applicable, the voting was read from
+    pub source: String,
+    // Identifier of the voting in the source, e.g. the "anr" of Swissvotes
+    pub id: Option<String>,
+    pub date: NaiveDate,
+    // Pairs of language and title
+    pub titles: Vec<(String, String)>,
+    pub total_voters: Option<u32>,
+    pub ballots_returned: Option<u32>,
+    pub total_yes: Option<u32>,
+    pub total_no: Option<u32>,
+    pub cantons_voting_yes: Option<Decimal>,
+    pub cantons_voting_no: Option<Decimal>,
+    pub accepted: Option<bool>,
+}
+
+// A source that cannot be read at all, as opposed to a line of it that is ignored
+#[derive(Debug)]
+pub enum SourceError {
+    Io {
+        path: String,
+        message: String,
+    },
+    MissingColumns {
+        path: String,
+        columns: Vec<&'static str>,
+    },
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Io { path, message } => {
+                write!(f, "could not read '{}': {}", path, message)
+            }
+            SourceError::MissingColumns { path, columns } => write!(
+                f,
+                "'{}' is not a Swissvotes dataset, missing columns: {}",
+                path,
+                columns.join(", ")
+            ),
+        }
+    }
+}
+
+pub struct Discrepancy {
+    pub no: Option<u32>,
+    pub title: String,
+    pub source: String,
+    pub column: &'static str,
+    pub ours: Option<String>,
+    pub theirs: String,
+}
+
+#[derive(Default)]
+pub struct Reconciliation {
+    pub compared: usize,
+    // Votings of the other source that match none or several votings of the dataset
+    pub unmatched: Vec<(NaiveDate, String, String)>,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+fn integer(value: Option<u32>) -> Option<String> {
+    value.map(|value| value.to_string())
+}
+
+fn decimal(value: Option<Decimal>) -> Option<String> {
+    // The scale depends on the source, e.g. 20 and 20.0 cantons are the same
+    value.map(|value| value.normalize().to_string())
+}
+
+// The other sources only tell whether a voting was accepted. Which proposal was preferred in a
+// tie-break cannot be compared, hence `None`.
+fn accepted(outcome: Option<Outcome>) -> Option<Option<String>> {
+    match outcome {
+        Some(Outcome::Accepted) => Some(Some(Outcome::Accepted.to_string())),
+        Some(Outcome::Rejected | Outcome::RejectedByTheCantons | Outcome::RejectedByThePeople) => {
+            Some(Some(Outcome::Rejected.to_string()))
+        }
+        Some(Outcome::CounterProposalPreferred | Outcome::InitiativePreferred) => None,
+        None => Some(None),
+    }
+}
+
+pub fn reconcile(data: &Data, references: &[ReferenceVoting]) -> Reconciliation {
+    let mut reconciliation = Reconciliation::default();
+    for reference in references {
+        let title = reference
+            .titles
+            .first()
+            .map(|(_, title)| title.clone())
+            .unwrap_or_default();
+        let Some(idx) = match_voting(data, reference.date, &reference.titles) else {
+            reconciliation
+                .unmatched
+                .push((reference.date, title, reference.source.clone()));
+            continue;
+        };
+        reconciliation.compared += 1;
+
+        let mut fields = vec![
+            (
+                TOTAL_VOTERS,
+                integer(data.total_voters[idx]),
+                integer(reference.total_voters),
+            ),
+            (
+                BALLOTS_RETURNED,
+                integer(data.ballots_returned[idx]),
+                integer(reference.ballots_returned),
+            ),
+            (
+                TOTAL_YES,
+                integer(data.total_yes[idx]),
+                integer(reference.total_yes),
+            ),
+            (
+                TOTAL_NO,
+                integer(data.total_no[idx]),
+                integer(reference.total_no),
+            ),
+            (
+                CANTONS_VOTING_YES,
+                decimal(data.cantons_voting_yes[idx].value()),
+                decimal(reference.cantons_voting_yes),
+            ),
+            (
+                CANTONS_VOTING_NO,
+                decimal(data.cantons_voting_no[idx].value()),
+                decimal(reference.cantons_voting_no),
+            ),
+        ];
+        if let Some(outcome) = accepted(data.outcome[idx]) {
+            fields.push((
+                OUTCOME,
+                outcome,
+                reference.accepted.map(|accepted| {
+                    match accepted {
+                        true => Outcome::Accepted,
+                        false => Outcome::Rejected,
+                    }
+                    .to_string()
+                }),
+            ));
+        }
+
+        for (column, ours, theirs) in fields {
+            let Some(theirs) = theirs else {
+                continue;
+            };
+            if ours.as_ref() != Some(&theirs) {
+                reconciliation.discrepancies.push(Discrepancy {
+                    no: data.no[idx],
+                    title: data.title_it[idx].clone(),
+                    source: reference.source.clone(),
+                    column,
+                    ours,
+                    theirs,
+                });
+            }
+        }
+    }
+    reconciliation
+}
+
+fn number_to_string(no: Option<u32>) -> String {
+    match no {
+        Some(no) => no.to_string(),
+        None => "?".to_string(),
+    }
+}
+
+pub fn reconciliation_to_text(reconciliation: &Reconciliation) -> String {
+    let mut lines: Vec<String> = vec![format!(
+        "{} votings compared, {} not matched, {} discrepancies",
+        reconciliation.compared,
+        reconciliation.unmatched.len(),
+        reconciliation.discrepancies.len()
+    )];
+
+    for (date, title, source) in &reconciliation.unmatched {
+        lines.push(format!("? {} '{}' ({})", date, title, source));
+    }
+    for discrepancy in &reconciliation.discrepancies {
+        lines.push(format!(
+            "! voting {}, {}: {} in the dataset, {} in {}",
+            number_to_string(discrepancy.no),
+            discrepancy.column,
+            discrepancy.ours.as_deref().unwrap_or("null"),
+            discrepancy.theirs,
+            discrepancy.source
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn reconciliation_to_json(reconciliation: &Reconciliation) -> Value {
+    json!({
+        "compared": reconciliation.compared,
+        "unmatched": reconciliation
+            .unmatched
+            .iter()
+            .map(|(date, title, source)| json!({
+                "date": date.to_string(),
+                "title": title,
+                "source": source,
+            }))
+            .collect::<Vec<_>>(),
+        "discrepancies": reconciliation
+            .discrepancies
+            .iter()
+            .map(|discrepancy| json!({
+                "no": discrepancy.no,
+                "title": discrepancy.title,
+                "column": discrepancy.column,
+                "dataset": discrepancy.ours,
+                "source": discrepancy.source,
+                "other": discrepancy.theirs,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Row, Tristate};
+
+    use super::*;
+
+    fn voting() -> Row {
+        Row {
+            no: Some(631),
+            date_of_voting: NaiveDate::from_ymd_opt(2020, 9, 27),
+            title_de: "Volksinitiative «Für eine massvolle Zuwanderung (Begrenzungsinitiative)»"
+                .to_string(),
+            total_voters: Some(5_479_123),
+            ballots_returned: Some(3_261_344),
+            total_yes: Some(1_233_995),
+            total_no: Some(1_988_349),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            cantons_voting_no: Tristate::Value(Decimal::new(195, 1)),
+            outcome: Some(Outcome::Rejected),
+            ..Default::default()
+        }
+    }
+
+    fn reference() -> ReferenceVoting {
+        ReferenceVoting {
+            source: "swissvotes.csv:2".to_string(),
+            id: Some("631".to_string()),
+            date: NaiveDate::from_ymd_opt(2020, 9, 27).unwrap(),
+            titles: vec![(
+                "de".to_string(),
+                "Volksinitiative «Für eine massvolle Zuwanderung (Begrenzungsinitiative)»"
+                    .to_string(),
+            )],
+            total_voters: Some(5_479_123),
+            ballots_returned: Some(3_261_344),
+            total_yes: Some(1_233_995),
+            total_no: Some(1_988_349),
+            // The scale of the source differs, e.g. 19.50
+            cantons_voting_yes: Some(Decimal::new(350, 2)),
+            cantons_voting_no: Some(Decimal::new(1950, 2)),
+            accepted: Some(false),
+        }
+    }
+
+    fn reconcile_with(reference: ReferenceVoting) -> Reconciliation {
+        let mut data = Data::default();
+        data.update(voting());
+        reconcile(&data, &[reference])
+    }
+
+    fn columns(reconciliation: &Reconciliation) -> Vec<&'static str> {
+        reconciliation
+            .discrepancies
+            .iter()
+            .map(|discrepancy| discrepancy.column)
+            .collect()
+    }
+
+    #[test]
+    fn matching_results_have_no_discrepancy() {
+        let reconciliation = reconcile_with(reference());
+        assert_eq!(reconciliation.compared, 1);
+        assert!(reconciliation.unmatched.is_empty());
+        assert!(reconciliation.discrepancies.is_empty());
+    }
+
+    #[test]
+    fn another_canton_vote_is_a_discrepancy() {
+        let reconciliation = reconcile_with(ReferenceVoting {
+            cantons_voting_yes: Some(Decimal::new(4, 0)),
+            cantons_voting_no: Some(Decimal::new(19, 0)),
+            ..reference()
+        });
+        assert_eq!(
+            columns(&reconciliation),
+            vec![CANTONS_VOTING_YES, CANTONS_VOTING_NO]
+        );
+        let discrepancy = &reconciliation.discrepancies[0];
+        assert_eq!(discrepancy.no, Some(631));
+        assert_eq!(discrepancy.ours.as_deref(), Some("3.5"));
+        assert_eq!(discrepancy.theirs, "4");
+    }
+
+    #[test]
+    fn an_accepted_voting_rejected_in_the_dataset_is_a_discrepancy() {
+        let reconciliation = reconcile_with(ReferenceVoting {
+            accepted: Some(true),
+            ..reference()
+        });
+        assert_eq!(columns(&reconciliation), vec![OUTCOME]);
+        let discrepancy = &reconciliation.discrepancies[0];
+        assert_eq!(discrepancy.ours, Some(Outcome::Rejected.to_string()));
+        assert_eq!(discrepancy.theirs, Outcome::Accepted.to_string());
+    }
+
+    #[test]
+    fn a_voting_of_another_day_is_not_matched() {
+        let reconciliation = reconcile_with(ReferenceVoting {
+            date: NaiveDate::from_ymd_opt(2020, 11, 29).unwrap(),
+            ..reference()
+        });
+        assert_eq!(reconciliation.compared, 0);
+        assert_eq!(reconciliation.unmatched.len(), 1);
+    }
+}
@@ -0,0 +1,575 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+
+use crate::converters::{
+    date_to_decade, date_to_legislature_period, date_to_quarter, date_to_weekday,
+};
+use crate::data::{cantons_voting_status, Data};
+use chrono::{Datelike, SecondsFormat};
+
+// Versioned migrations of the schema. The version of a database is kept in `PRAGMA user_version`,
+// so new migrations must be appended and existing ones never modified. Databases written before
+// the schema was versioned have version 0 and may already have some of the columns, which are
+// therefore only added when they do not exist.
+enum Step {
+    Execute(&'static str),
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+}
+
+const MIGRATIONS: [(u32, &[Step]); 5] = [
+    (
+        1,
+        &[Step::Execute(
+            "
+            CREATE TABLE IF NOT EXISTS voting_days (
+                date_of_voting TEXT PRIMARY KEY,
+                year INTEGER NOT NULL,
+                decade INTEGER NOT NULL,
+                quarter INTEGER NOT NULL,
+                weekday TEXT NOT NULL,
+                legislature_period INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS votings (
+                no INTEGER PRIMARY KEY,
+                date_of_voting TEXT REFERENCES voting_days (date_of_voting),
+                kind TEXT NOT NULL,
+                outcome TEXT,
+                total_voters INTEGER,
+                domestic_voters INTEGER,
+                overseas_voters INTEGER,
+                ballots_returned INTEGER,
+                participation REAL,
+                invalid_voting_ballots INTEGER,
+                blank_voting_ballots INTEGER,
+                valid_voting_ballots INTEGER,
+                total_yes INTEGER,
+                ratio_yes REAL,
+                total_no INTEGER,
+                ratio_no REAL,
+                source_url_it TEXT NOT NULL,
+                source_url_de TEXT NOT NULL,
+                source_url_fr TEXT NOT NULL,
+                fetched_at TEXT,
+                content_sha256 TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS votings_date_of_voting ON votings (date_of_voting);
+            CREATE INDEX IF NOT EXISTS votings_kind ON votings (kind);
+            CREATE TABLE IF NOT EXISTS titles (
+                no INTEGER NOT NULL REFERENCES votings (no) ON DELETE CASCADE,
+                language TEXT NOT NULL,
+                title TEXT NOT NULL,
+                PRIMARY KEY (no, language)
+            );
+            CREATE TABLE IF NOT EXISTS canton_results (
+                no INTEGER PRIMARY KEY REFERENCES votings (no) ON DELETE CASCADE,
+                cantons_voting_yes REAL,
+                cantons_voting_no REAL
+            );
+            CREATE TABLE IF NOT EXISTS recommendations (
+                no INTEGER PRIMARY KEY REFERENCES votings (no) ON DELETE CASCADE,
+                recommendation TEXT NOT NULL,
+                supplementary_url TEXT
+            );
+            ",
+        )],
+    ),
+    (
+        2,
+        &[
+            Step::AddColumn {
+                table: "votings",
+                column: "overseas_voters_status",
+                definition: "TEXT NOT NULL DEFAULT 'missing'",
+            },
+            Step::AddColumn {
+                table: "canton_results",
+                column: "status",
+                definition: "TEXT NOT NULL DEFAULT 'missing'",
+            },
+            Step::Execute(
+                "
+                UPDATE votings SET overseas_voters_status = 'value'
+                    WHERE overseas_voters IS NOT NULL;
+                UPDATE canton_results SET status = 'value'
+                    WHERE cantons_voting_yes IS NOT NULL AND cantons_voting_no IS NOT NULL;
+                ",
+            ),
+        ],
+    ),
+    (
+        3,
+        &[Step::AddColumn {
+            table: "votings",
+            column: "status",
+            definition: "TEXT NOT NULL DEFAULT 'completed'",
+        }],
+    ),
+    (
+        4,
+        &[
+            Step::AddColumn {
+                table: "votings",
+                column: "result_status",
+                definition: "TEXT",
+            },
+            Step::AddColumn {
+                table: "votings",
+                column: "validated_on",
+                definition: "TEXT",
+            },
+        ],
+    ),
+    (
+        5,
+        // Percentages and cantonal votes are kept as decimal text at their published precision.
+        // SQLite cannot change the type of a column, hence the tables are copied.
+        &[Step::Execute(
+            "
+            CREATE TABLE votings_new (
+                no INTEGER PRIMARY KEY,
+                date_of_voting TEXT REFERENCES voting_days (date_of_voting),
+                kind TEXT NOT NULL,
+                outcome TEXT,
+                total_voters INTEGER,
+                domestic_voters INTEGER,
+                overseas_voters INTEGER,
+                ballots_returned INTEGER,
+                participation TEXT,
+                invalid_voting_ballots INTEGER,
+                blank_voting_ballots INTEGER,
+                valid_voting_ballots INTEGER,
+                total_yes INTEGER,
+                ratio_yes TEXT,
+                total_no INTEGER,
+                ratio_no TEXT,
+                source_url_it TEXT NOT NULL,
+                source_url_de TEXT NOT NULL,
+                source_url_fr TEXT NOT NULL,
+                fetched_at TEXT,
+                content_sha256 TEXT NOT NULL,
+                overseas_voters_status TEXT NOT NULL DEFAULT 'missing',
+                status TEXT NOT NULL DEFAULT 'completed',
+                result_status TEXT,
+                validated_on TEXT
+            );
+            INSERT INTO votings_new (
+                no, date_of_voting, kind, outcome, total_voters, domestic_voters, overseas_voters,
+                ballots_returned, participation, invalid_voting_ballots, blank_voting_ballots,
+                valid_voting_ballots, total_yes, ratio_yes, total_no, ratio_no, source_url_it,
+                source_url_de, source_url_fr, fetched_at, content_sha256, overseas_voters_status,
+                status, result_status, validated_on
+            )
+            SELECT
+                no, date_of_voting, kind, outcome, total_voters, domestic_voters, overseas_voters,
+                ballots_returned, participation, invalid_voting_ballots, blank_voting_ballots,
+                valid_voting_ballots, total_yes, ratio_yes, total_no, ratio_no, source_url_it,
+                source_url_de, source_url_fr, fetched_at, content_sha256, overseas_voters_status,
+                status, result_status, validated_on
+            FROM votings;
+            DROP TABLE votings;
+            ALTER TABLE votings_new RENAME TO votings;
+            CREATE INDEX votings_date_of_voting ON votings (date_of_voting);
+            CREATE INDEX votings_kind ON votings (kind);
+
+            CREATE TABLE canton_results_new (
+                no INTEGER PRIMARY KEY REFERENCES votings (no) ON DELETE CASCADE,
+                cantons_voting_yes TEXT,
+                cantons_voting_no TEXT,
+                status TEXT NOT NULL DEFAULT 'missing'
+            );
+            INSERT INTO canton_results_new (no, cantons_voting_yes, cantons_voting_no, status)
+            SELECT no, cantons_voting_yes, cantons_voting_no, status FROM canton_results;
+            DROP TABLE canton_results;
+            ALTER TABLE canton_results_new RENAME TO canton_results;
+            ",
+        )],
+    ),
+];
+
+// Tables in the order in which they can be dropped without violating foreign keys
+const TABLES: [&str; 5] = [
+    "recommendations",
+    "canton_results",
+    "titles",
+    "votings",
+    "voting_days",
+];
+
+fn has_column(connection: &Connection, table: &str, column: &str) -> bool {
+    connection
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |row| row.get::<_, u32>(0),
+        )
+        .expect("Could not read the columns of the SQLite database")
+        > 0
+}
+
+fn migrate(connection: &mut Connection) {
+    let version: u32 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .expect("Could not read the version of the SQLite database");
+
+    for (migration_version, steps) in MIGRATIONS {
+        if migration_version <= version {
+            continue;
+        }
+        let transaction = connection
+            .transaction()
+            .expect("Could not start a transaction");
+        for step in steps {
+            match step {
+                Step::Execute(statements) => transaction
+                    .execute_batch(statements)
+                    .expect("Could not migrate the SQLite database"),
+                Step::AddColumn {
+                    table,
+                    column,
+                    definition,
+                } => {
+                    if !has_column(&transaction, table, column) {
+                        transaction
+                            .execute_batch(&format!(
+                                "ALTER TABLE {} ADD COLUMN {} {};",
+                                table, column, definition
+                            ))
+                            .expect("Could not migrate the SQLite database");
+                    }
+                }
+            }
+        }
+        transaction
+            .pragma_update(None, "user_version", migration_version)
+            .expect("Could not record the version of the SQLite database");
+        transaction
+            .commit()
+            .expect("Could not commit the migration");
+        info!(
+            "Applied migration {} of the SQLite database",
+            migration_version
+        );
+    }
+}
+
+pub fn save_as_sqlite(data: &Data, path: &str, upsert: bool) {
+    let mut connection = Connection::open(path).expect("Could not open the SQLite database");
+
+    // Without upsert the database contains exactly the extracted data
+    if !upsert {
+        for table in TABLES {
+            connection
+                .execute_batch(&format!("DROP TABLE IF EXISTS {};", table))
+                .expect("Could not drop table");
+        }
+        connection
+            .pragma_update(None, "user_version", 0)
+            .expect("Could not reset the version of the SQLite database");
+    }
+    // Migrations copy tables, which must not delete the rows referencing them
+    connection
+        .execute_batch("PRAGMA foreign_keys = OFF;")
+        .expect("Could not disable foreign keys");
+    migrate(&mut connection);
+    connection
+        .execute_batch("PRAGMA foreign_keys = ON;")
+        .expect("Could not enable foreign keys");
+
+    let transaction = connection
+        .transaction()
+        .expect("Could not start a transaction");
+    for idx in 0..data.no.len() {
+        let Some(no) = data.no[idx] else {
+            warn!(
+                "Voting {} has no number and is not written to the SQLite database",
+                data.source_url_it[idx]
+            );
+            continue;
+        };
+
+        let date_of_voting = data.date_of_voting[idx];
+        if let Some(date) = date_of_voting {
+            transaction
+                .execute(
+                    "INSERT INTO voting_days (
+                        date_of_voting, year, decade, quarter, weekday, legislature_period
+                     ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (date_of_voting) DO NOTHING",
+                    params![
+                        date.format("%Y-%m-%d").to_string(),
+                        date.year(),
+                        date_to_decade(&date),
+                        date_to_quarter(&date),
+                        date_to_weekday(&date),
+                        date_to_legislature_period(&date),
+                    ],
+                )
+                .expect("Could not write voting day");
+        }
+
+        transaction
+            .execute(
+                "INSERT INTO votings (
+                    no, date_of_voting, kind, outcome, total_voters, domestic_voters,
+                    overseas_voters, overseas_voters_status, ballots_returned, participation,
+                    invalid_voting_ballots, blank_voting_ballots, valid_voting_ballots, total_yes,
+                    ratio_yes, total_no, ratio_no, source_url_it, source_url_de, source_url_fr,
+                    fetched_at, content_sha256, status, result_status, validated_on
+                 ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11,
+                    ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25
+                 )
+                 ON CONFLICT (no) DO UPDATE SET
+                    date_of_voting = excluded.date_of_voting,
+                    kind = excluded.kind,
+                    outcome = excluded.outcome,
+                    total_voters = excluded.total_voters,
+                    domestic_voters = excluded.domestic_voters,
+                    overseas_voters = excluded.overseas_voters,
+                    overseas_voters_status = excluded.overseas_voters_status,
+                    ballots_returned = excluded.ballots_returned,
+                    participation = excluded.participation,
+                    invalid_voting_ballots = excluded.invalid_voting_ballots,
+                    blank_voting_ballots = excluded.blank_voting_ballots,
+                    valid_voting_ballots = excluded.valid_voting_ballots,
+                    total_yes = excluded.total_yes,
+                    ratio_yes = excluded.ratio_yes,
+                    total_no = excluded.total_no,
+                    ratio_no = excluded.ratio_no,
+                    source_url_it = excluded.source_url_it,
+                    source_url_de = excluded.source_url_de,
+                    source_url_fr = excluded.source_url_fr,
+                    fetched_at = excluded.fetched_at,
+                    content_sha256 = excluded.content_sha256,
+                    status = excluded.status,
+                    result_status = excluded.result_status,
+                    validated_on = excluded.validated_on",
+                params![
+                    no,
+                    date_of_voting.map(|date| date.format("%Y-%m-%d").to_string()),
+                    data.kind[idx],
+                    data.outcome[idx].map(|outcome| outcome.as_str()),
+                    data.total_voters[idx],
+                    data.domestic_voters[idx],
+                    data.overseas_voters[idx].value(),
+                    data.overseas_voters[idx].status(),
+                    data.ballots_returned[idx],
+                    decimal_to_text(data.participation[idx]),
+                    data.invalid_voting_ballots[idx],
+                    data.blank_voting_ballots[idx],
+                    data.valid_voting_ballots[idx],
+                    data.total_yes[idx],
+                    decimal_to_text(data.ratio_yes[idx]),
+                    data.total_no[idx],
+                    decimal_to_text(data.ratio_no[idx]),
+                    data.source_url_it[idx],
+                    data.source_url_de[idx],
+                    data.source_url_fr[idx],
+                    data.fetched_at[idx]
+                        .map(|timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                    data.content_sha256[idx],
+                    data.status[idx].as_str(),
+                    data.result_status[idx].map(|status| status.as_str()),
+                    data.validated_on[idx].map(|date| date.format("%Y-%m-%d").to_string()),
+                ],
+            )
+            .expect("Could not write voting");
+
+        for (language, title) in [
+            ("it", &data.title_it[idx]),
+            ("de", &data.title_de[idx]),
+            ("fr", &data.title_fr[idx]),
+        ] {
+            transaction
+                .execute(
+                    "INSERT INTO titles (no, language, title) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (no, language) DO UPDATE SET title = excluded.title",
+                    params![no, language, title],
+                )
+                .expect("Could not write title");
+        }
+
+        transaction
+            .execute(
+                "INSERT INTO canton_results (
+                    no, cantons_voting_yes, cantons_voting_no, status
+                 ) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (no) DO UPDATE SET
+                    cantons_voting_yes = excluded.cantons_voting_yes,
+                    cantons_voting_no = excluded.cantons_voting_no,
+                    status = excluded.status",
+                params![
+                    no,
+                    decimal_to_text(data.cantons_voting_yes[idx].value()),
+                    decimal_to_text(data.cantons_voting_no[idx].value()),
+                    cantons_voting_status(
+                        data.cantons_voting_yes[idx],
+                        data.cantons_voting_no[idx]
+                    ),
+                ],
+            )
+            .expect("Could not write canton results");
+
+        // Only popular initiatives carry a recommendation of the Federal Assembly
+        if let Some(recommendation) = &data.recommendation[idx] {
+            transaction
+                .execute(
+                    "INSERT INTO recommendations (no, recommendation, supplementary_url)
+                     VALUES (?1, ?2, ?3)
+                     ON CONFLICT (no) DO UPDATE SET
+                        recommendation = excluded.recommendation,
+                        supplementary_url = excluded.supplementary_url",
+                    params![no, recommendation, data.supplementary_url[idx]],
+                )
+                .expect("Could not write recommendation");
+        }
+    }
+    transaction
+        .commit()
+        .expect("Could not commit the SQLite database");
+}
+
+fn decimal_to_text(value: Option<Decimal>) -> Option<String> {
+    value.map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::data::{ResultStatus, Row, Tristate, VotingStatus};
+    use crate::loader::load_from_sqlite;
+
+    fn database(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn data() -> Data {
+        let mut data = Data::default();
+        data.update(Row {
+            no: Some(640),
+            date_of_voting: NaiveDate::from_ymd_opt(2020, 9, 27),
+            kind: "initiative".to_string(),
+            total_voters: Some(5_479_123),
+            overseas_voters: Tristate::Value(193_460),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            cantons_voting_no: Tristate::Value(Decimal::new(195, 1)),
+            participation: Some(Decimal::new(5910, 4)),
+            ratio_yes: Some(Decimal::new(3830, 4)),
+            ..Default::default()
+        });
+        data
+    }
+
+    fn version(connection: &Connection) -> u32 {
+        connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn upsert_migrates_a_database_of_the_first_schema() {
+        let path = database("first-schema");
+        let connection = Connection::open(&path).unwrap();
+        let Step::Execute(first_schema) = MIGRATIONS[0].1[0] else {
+            unreachable!()
+        };
+        connection.execute_batch(first_schema).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO votings VALUES (
+                    1, NULL, 'referendum', NULL, 100, NULL, NULL, 90, 0.9, 0, 0, 90, 45, 0.5,
+                    45, 0.5, 'it', 'de', 'fr', NULL, 'sha'
+                );
+                INSERT INTO canton_results VALUES (1, NULL, NULL);
+                INSERT INTO titles VALUES (1, 'it', 'Legge');",
+            )
+            .unwrap();
+        drop(connection);
+
+        save_as_sqlite(&data(), path.to_str().unwrap(), true);
+        save_as_sqlite(&data(), path.to_str().unwrap(), true);
+
+        let connection = Connection::open(&path).unwrap();
+        assert_eq!(version(&connection), MIGRATIONS.last().unwrap().0);
+        let statuses: Vec<(u32, String, String)> = connection
+            .prepare(
+                "SELECT v.no, v.overseas_voters_status, c.status
+                 FROM votings v JOIN canton_results c ON c.no = v.no ORDER BY v.no",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, "missing".to_string(), "missing".to_string()),
+                (640, "value".to_string(), "value".to_string()),
+            ]
+        );
+        let participation: (String, String) = connection
+            .query_row(
+                "SELECT typeof(participation), participation FROM votings WHERE no = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(participation, ("text".to_string(), "0.9".to_string()));
+        let titles: u32 = connection
+            .query_row("SELECT COUNT(*) FROM titles WHERE no = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(titles, 1);
+    }
+
+    #[test]
+    fn migrations_skip_columns_of_an_unversioned_database() {
+        let path = database("unversioned");
+        save_as_sqlite(&data(), path.to_str().unwrap(), false);
+        let connection = Connection::open(&path).unwrap();
+        connection.pragma_update(None, "user_version", 0).unwrap();
+        drop(connection);
+
+        save_as_sqlite(&data(), path.to_str().unwrap(), true);
+        assert_eq!(
+            version(&Connection::open(&path).unwrap()),
+            MIGRATIONS.last().unwrap().0
+        );
+    }
+
+    #[test]
+    fn a_new_database_is_loaded_back() {
+        let path = database("round-trip");
+        let mut data = data();
+        data.result_status[0] = Some(ResultStatus::Final);
+        data.validated_on[0] = NaiveDate::from_ymd_opt(2020, 11, 3);
+        save_as_sqlite(&data, path.to_str().unwrap(), false);
+
+        let loaded = load_from_sqlite(path.to_str().unwrap()).ok().unwrap();
+        assert_eq!(loaded.no, vec![Some(640)]);
+        assert_eq!(loaded.status, vec![VotingStatus::Completed]);
+        assert_eq!(loaded.result_status, vec![Some(ResultStatus::Final)]);
+        assert_eq!(loaded.validated_on, data.validated_on);
+        assert_eq!(loaded.overseas_voters, data.overseas_voters);
+        assert_eq!(loaded.cantons_voting_yes, data.cantons_voting_yes);
+        // Decimals keep their published precision, e.g. 59.10% is not 0.591
+        assert_eq!(
+            loaded.participation[0].map(|value| value.to_string()),
+            Some("0.5910".to_string())
+        );
+        assert_eq!(loaded.ratio_yes, data.ratio_yes);
+    }
+}
@@ -0,0 +1,92 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{info, warn};
+use polars::frame::DataFrame;
+use serde_json::json;
+
+use crate::constants::{NUMBER_OF_SLOWEST_PAGES, RUN_SUMMARY_FILE};
+
+// Statistics are collected from everywhere in the pipeline, hence they live in a global
+#[derive(Default)]
+struct RunStatistics {
+    pages_fetched: u32,
+    cache_hits: u32,
+    retries: u32,
+    validation_warnings: Vec<String>,
+    page_durations: Vec<(String, Duration)>,
+}
+
+static STATISTICS: Mutex<Option<RunStatistics>> = Mutex::new(None);
+
+fn with_statistics(update: impl FnOnce(&mut RunStatistics)) {
+    let mut statistics = STATISTICS.lock().unwrap();
+    update(statistics.get_or_insert_with(RunStatistics::default));
+}
+
+pub fn record_page_fetched(url: &str, duration: Duration) {
+    with_statistics(|statistics| {
+        statistics.pages_fetched += 1;
+        statistics.page_durations.push((url.to_string(), duration));
+    });
+}
+
+pub fn record_cache_hit() {
+    with_statistics(|statistics| statistics.cache_hits += 1);
+}
+
+pub fn record_retry() {
+    with_statistics(|statistics| statistics.retries += 1);
+}
+
+pub fn record_validation_warning(warning: String) {
+    warn!("{}", warning);
+    with_statistics(|statistics| statistics.validation_warnings.push(warning));
+}
+
+pub fn save_run_summary(df: &DataFrame, started_at: DateTime<Utc>) {
+    let statistics = STATISTICS.lock().unwrap().take().unwrap_or_default();
+
+    let null_counts: HashMap<&str, usize> = df
+        .get_columns()
+        .iter()
+        .map(|column| (column.name().as_str(), column.null_count()))
+        .collect();
+
+    let mut page_durations = statistics.page_durations;
+    page_durations.sort_by_key(|(_, duration)| Reverse(*duration));
+    let slowest_pages: Vec<_> = page_durations
+        .iter()
+        .take(NUMBER_OF_SLOWEST_PAGES)
+        .map(|(url, duration)| json!({ "url": url, "seconds": duration.as_secs_f64() }))
+        .collect();
+
+    info!(
+        "Run summary: {} pages fetched, {} cache hits, {} retries, {} rows, {} validation warnings",
+        statistics.pages_fetched,
+        statistics.cache_hits,
+        statistics.retries,
+        df.height(),
+        statistics.validation_warnings.len()
+    );
+
+    let completed_at = Utc::now();
+    let summary = json!({
+        "started_at": started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "completed_at": completed_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "run_time_seconds": (completed_at - started_at).num_seconds(),
+        "pages_fetched": statistics.pages_fetched,
+        "cache_hits": statistics.cache_hits,
+        "retries": statistics.retries,
+        "rows": df.height(),
+        "null_counts": null_counts,
+        "validation_warnings": statistics.validation_warnings,
+        "slowest_pages": slowest_pages,
+    });
+    let file = File::create(RUN_SUMMARY_FILE).expect("could not create file");
+    serde_json::to_writer_pretty(file, &summary).expect("Could not save the run summary");
+}
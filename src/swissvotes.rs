@@ -0,0 +1,229 @@
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use log::warn;
+use rust_decimal::Decimal;
+
+use crate::converters::convert_date_to_iso_format;
+use crate::reconcile::{ReferenceVoting, SourceError};
+
+// Columns of the dataset of Swissvotes (swissvotes.ch), exported as CSV separated by semicolons.
+// Missing values are written as "." and read as `None`.
+const ID: &str = "anr";
+const DATE: &str = "datum";
+const TITLES: [(&str, &str); 4] = [
+    ("de", "titel_kurz_d"),
+    ("fr", "titel_kurz_f"),
+    ("de", "titel_off_d"),
+    ("fr", "titel_off_f"),
+];
+const TOTAL_VOTERS: &str = "berecht";
+const BALLOTS_RETURNED: &str = "stimmen";
+const TOTAL_YES: &str = "volkja";
+const TOTAL_NO: &str = "volknein";
+const CANTONS_VOTING_YES: &str = "kt-ja";
+const CANTONS_VOTING_NO: &str = "kt-nein";
+const ACCEPTED: &str = "annahme";
+
+// Columns read from every line, checked once against the header
+const COLUMNS: [&str; 13] = [
+    ID,
+    DATE,
+    TITLES[0].1,
+    TITLES[1].1,
+    TITLES[2].1,
+    TITLES[3].1,
+    TOTAL_VOTERS,
+    BALLOTS_RETURNED,
+    TOTAL_YES,
+    TOTAL_NO,
+    CANTONS_VOTING_YES,
+    CANTONS_VOTING_NO,
+    ACCEPTED,
+];
+
+struct Record<'a> {
+    headers: &'a csv::StringRecord,
+    record: &'a csv::StringRecord,
+}
+
+impl Record<'_> {
+    fn cell(&self, column: &str) -> Option<&str> {
+        let position = self.headers.iter().position(|header| header == column)?;
+        self.record
+            .get(position)
+            .map(str::trim)
+            .filter(|value| *value != ".")
+    }
+
+    fn parse<T: FromStr>(&self, column: &str) -> Option<T> {
+        self.cell(column)?.parse::<T>().ok()
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    // Releases of the dataset write dates either as on the Chancellery pages or in ISO-8601
+    convert_date_to_iso_format(value).or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok())
+}
+
+fn missing_columns(headers: &csv::StringRecord) -> Vec<&'static str> {
+    COLUMNS
+        .into_iter()
+        .filter(|column| !headers.iter().any(|header| header == *column))
+        .collect()
+}
+
+pub fn swissvotes_reference_votings(path: &str) -> Result<Vec<ReferenceVoting>, SourceError> {
+    let io_error = |error: csv::Error| SourceError::Io {
+        path: path.to_string(),
+        message: error.to_string(),
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_path(path)
+        .map_err(io_error)?;
+    let headers = reader.headers().map_err(io_error)?.clone();
+
+    // A column renamed by a new release of the dataset would otherwise be missing in every voting
+    let missing = missing_columns(&headers);
+    if !missing.is_empty() {
+        return Err(SourceError::MissingColumns {
+            path: path.to_string(),
+            columns: missing,
+        });
+    }
+
+    let mut votings = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        let Ok(record) = record else {
+            warn!("Ignored invalid line {} of {}", idx + 2, path);
+            continue;
+        };
+        let record = Record {
+            headers: &headers,
+            record: &record,
+        };
+        let Some(date) = record.cell(DATE).and_then(parse_date) else {
+            warn!("Ignored line {} of {} without a valid date", idx + 2, path);
+            continue;
+        };
+
+        votings.push(ReferenceVoting {
+            source: format!("{}:{}", path, idx + 2),
+            id: record
+                .cell(ID)
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string()),
+            date,
+            titles: TITLES
+                .iter()
+                .filter_map(|(language, column)| {
+                    record
+                        .cell(column)
+                        .filter(|title| !title.is_empty())
+                        .map(|title| (language.to_string(), title.to_string()))
+                })
+                .collect(),
+            total_voters: record.parse(TOTAL_VOTERS),
+            ballots_returned: record.parse(BALLOTS_RETURNED),
+            total_yes: record.parse(TOTAL_YES),
+            total_no: record.parse(TOTAL_NO),
+            cantons_voting_yes: record.parse::<Decimal>(CANTONS_VOTING_YES),
+            cantons_voting_no: record.parse::<Decimal>(CANTONS_VOTING_NO),
+            accepted: record.parse::<u8>(ACCEPTED).map(|accepted| accepted == 1),
+        });
+    }
+    Ok(votings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "tests/fixtures/swissvotes.csv";
+
+    #[test]
+    fn header_has_every_column() {
+        let headers = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .from_path(FIXTURE)
+            .unwrap()
+            .headers()
+            .unwrap()
+            .clone();
+        assert!(missing_columns(&headers).is_empty());
+
+        let headers = csv::StringRecord::from(vec!["anr", "datum", "kt_ja", "kt_nein"]);
+        assert_eq!(
+            missing_columns(&headers),
+            vec![
+                "titel_kurz_d",
+                "titel_kurz_f",
+                "titel_off_d",
+                "titel_off_f",
+                "berecht",
+                "stimmen",
+                "volkja",
+                "volknein",
+                "kt-ja",
+                "kt-nein",
+                "annahme"
+            ]
+        );
+    }
+
+    #[test]
+    fn votings_are_read_with_their_results() {
+        let votings = swissvotes_reference_votings(FIXTURE).unwrap();
+        assert_eq!(votings.len(), 3);
+
+        let voting = &votings[0];
+        assert_eq!(voting.source, format!("{}:2", FIXTURE));
+        assert_eq!(voting.id.as_deref(), Some("631"));
+        assert_eq!(voting.date, NaiveDate::from_ymd_opt(2020, 9, 27).unwrap());
+        assert_eq!(voting.titles.len(), 4);
+        assert_eq!(
+            voting.titles[0],
+            ("de".to_string(), "Begrenzungsinitiative".to_string())
+        );
+        assert_eq!(voting.total_voters, Some(5_479_123));
+        assert_eq!(voting.ballots_returned, Some(3_261_344));
+        assert_eq!(voting.total_yes, Some(1_233_995));
+        assert_eq!(voting.total_no, Some(1_988_349));
+        assert_eq!(voting.cantons_voting_yes, Some(Decimal::new(35, 1)));
+        assert_eq!(voting.cantons_voting_no, Some(Decimal::new(195, 1)));
+        assert_eq!(voting.accepted, Some(false));
+    }
+
+    #[test]
+    fn missing_values_are_none() {
+        let votings = swissvotes_reference_votings(FIXTURE).unwrap();
+        // Laws have no cantonal votes
+        assert_eq!(votings[1].cantons_voting_yes, None);
+        assert_eq!(votings[1].cantons_voting_no, None);
+
+        let voting = &votings[2];
+        assert_eq!(voting.id, None);
+        assert_eq!(voting.date, NaiveDate::from_ymd_opt(2027, 3, 7).unwrap());
+        assert_eq!(voting.titles.len(), 2);
+        assert_eq!(voting.total_voters, None);
+        assert_eq!(voting.accepted, None);
+    }
+
+    #[test]
+    fn a_file_of_another_dataset_is_an_error() {
+        let path = std::env::temp_dir().join(format!("not-swissvotes-{}.csv", std::process::id()));
+        std::fs::write(&path, "anr;datum;kt_ja;kt_nein\n631;27.09.2020;3.5;19.5\n").unwrap();
+        let result = swissvotes_reference_votings(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(SourceError::MissingColumns { columns, .. }) if columns.contains(&"kt-ja")
+        ));
+
+        assert!(matches!(
+            swissvotes_reference_votings("tests/fixtures/missing.csv"),
+            Err(SourceError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+use polars::df;
+use polars::frame::DataFrame;
+use rust_decimal::Decimal;
+
+use crate::cli::Locale;
+use crate::constants::{
+    ANSWER, ANSWERS_LONG_FILE, BALLOTS_RETURNED, BLANK_VOTING_BALLOTS, CANTON, CANTONS_VOTING_NO,
+    CANTONS_VOTING_YES, CANTON_ANSWERS_LONG_FILE, CANTON_NUMBER, COUNT, DOMESTIC_VOTERS,
+    INVALID_VOTING_BALLOTS, MEASURE, MEASURES_LONG_FILE, NO, OVERSEAS_VOTERS, PARTICIPATION,
+    RATIO_NO, RATIO_YES, SHARE, TOTAL_NO, TOTAL_VOTERS, TOTAL_YES, VALID_VOTING_BALLOTS, VALUE,
+};
+use crate::data::{save_dataframe, Data};
+use crate::fso::AreaResults;
+use crate::locale::save_localised;
+
+// Counts of one answer, with their share of all the answers given. For a voting these are the
+// ballots returned, so that the shares of the four answers add up to one, unlike the published
+// ratios which are shares of the valid ballots. Cantons only have the yes and no votes, whose
+// shares are of the valid ballots.
+#[derive(Default)]
+struct Answers {
+    no: Vec<Option<u32>>,
+    answer: Vec<&'static str>,
+    count: Vec<u32>,
+    share: Vec<Option<f64>>,
+}
+
+impl Answers {
+    fn update(
+        &mut self,
+        no: Option<u32>,
+        answers_given: Option<u32>,
+        answers: [(&'static str, Option<u32>); 4],
+    ) {
+        for (answer, count) in answers {
+            // Answers that were not published are left out, they are not zero
+            let Some(count) = count else {
+                continue;
+            };
+            self.no.push(no);
+            self.answer.push(answer);
+            self.count.push(count);
+            self.share.push(
+                answers_given
+                    .filter(|answers_given| *answers_given > 0)
+                    .map(|answers_given| count as f64 / answers_given as f64),
+            );
+        }
+    }
+}
+
+// Values are written as text, so that the decimals keep their published precision, e.g. 0.5910
+fn decimal_to_text(value: Option<Decimal>) -> Option<String> {
+    value.map(|value| value.to_string())
+}
+
+fn integer_to_text(value: Option<u32>) -> Option<String> {
+    value.map(|value| value.to_string())
+}
+
+// One row per voting and answer: yes, no, blank and invalid
+fn create_answers_long(data: &Data) -> DataFrame {
+    let mut answers = Answers::default();
+    for idx in 0..data.no.len() {
+        answers.update(
+            data.no[idx],
+            data.ballots_returned[idx],
+            [
+                ("yes", data.total_yes[idx]),
+                ("no", data.total_no[idx]),
+                ("blank", data.blank_voting_ballots[idx]),
+                ("invalid", data.invalid_voting_ballots[idx]),
+            ],
+        );
+    }
+
+    df!(
+        NO => answers.no,
+        ANSWER => answers.answer,
+        COUNT => answers.count,
+        SHARE => answers.share,
+    )
+    .unwrap()
+}
+
+// One row per voting and measure, with the measures named after the columns of the wide dataset.
+// Ratios are fractions and null values are left out.
+fn create_measures_long(data: &Data) -> DataFrame {
+    let mut nos: Vec<Option<u32>> = Vec::new();
+    let mut measures: Vec<&str> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+    for idx in 0..data.no.len() {
+        for (measure, value) in [
+            (TOTAL_VOTERS, integer_to_text(data.total_voters[idx])),
+            (DOMESTIC_VOTERS, integer_to_text(data.domestic_voters[idx])),
+            (
+                OVERSEAS_VOTERS,
+                integer_to_text(data.overseas_voters[idx].value()),
+            ),
+            (
+                BALLOTS_RETURNED,
+                integer_to_text(data.ballots_returned[idx]),
+            ),
+            (PARTICIPATION, decimal_to_text(data.participation[idx])),
+            (
+                INVALID_VOTING_BALLOTS,
+                integer_to_text(data.invalid_voting_ballots[idx]),
+            ),
+            (
+                BLANK_VOTING_BALLOTS,
+                integer_to_text(data.blank_voting_ballots[idx]),
+            ),
+            (
+                VALID_VOTING_BALLOTS,
+                integer_to_text(data.valid_voting_ballots[idx]),
+            ),
+            (TOTAL_YES, integer_to_text(data.total_yes[idx])),
+            (RATIO_YES, decimal_to_text(data.ratio_yes[idx])),
+            (TOTAL_NO, integer_to_text(data.total_no[idx])),
+            (RATIO_NO, decimal_to_text(data.ratio_no[idx])),
+            (
+                CANTONS_VOTING_YES,
+                decimal_to_text(data.cantons_voting_yes[idx].value()),
+            ),
+            (
+                CANTONS_VOTING_NO,
+                decimal_to_text(data.cantons_voting_no[idx].value()),
+            ),
+        ] {
+            if let Some(value) = value {
+                nos.push(data.no[idx]);
+                measures.push(measure);
+                values.push(value);
+            }
+        }
+    }
+
+    df!(
+        NO => nos,
+        MEASURE => measures,
+        VALUE => values,
+    )
+    .unwrap()
+}
+
+// One row per voting, canton and answer. The files of the Federal Statistical Office only have
+// the yes and no votes of the cantons.
+fn create_canton_answers_long(cantons: &AreaResults) -> DataFrame {
+    let mut answers = Answers::default();
+    let mut canton_numbers: Vec<String> = Vec::new();
+    let mut canton_names: Vec<String> = Vec::new();
+    for idx in 0..cantons.no.len() {
+        let rows = answers.no.len();
+        answers.update(
+            Some(cantons.no[idx]),
+            cantons.valid_voting_ballots[idx],
+            [
+                ("yes", cantons.total_yes[idx]),
+                ("no", cantons.total_no[idx]),
+                ("blank", None),
+                ("invalid", None),
+            ],
+        );
+        for _ in rows..answers.no.len() {
+            canton_numbers.push(cantons.number[idx].clone());
+            canton_names.push(cantons.name[idx].clone());
+        }
+    }
+
+    df!(
+        NO => answers.no,
+        CANTON_NUMBER => canton_numbers,
+        CANTON => canton_names,
+        ANSWER => answers.answer,
+        COUNT => answers.count,
+        SHARE => answers.share,
+    )
+    .unwrap()
+}
+
+pub fn save_long_format(data: &Data, cantons: Option<&AreaResults>, locale: Option<Locale>) {
+    let mut dataframes = vec![
+        (ANSWERS_LONG_FILE, create_answers_long(data)),
+        (MEASURES_LONG_FILE, create_measures_long(data)),
+    ];
+    if let Some(cantons) = cantons {
+        dataframes.push((
+            CANTON_ANSWERS_LONG_FILE,
+            create_canton_answers_long(cantons),
+        ));
+    }
+
+    for (path, mut df) in dataframes {
+        save_dataframe(&mut df, path);
+        if let Some(locale) = locale {
+            save_localised(&df, path, locale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Row, Tristate};
+
+    use super::*;
+
+    #[test]
+    fn measures_keep_the_published_precision() {
+        let mut data = Data::default();
+        data.update(Row {
+            no: Some(631),
+            total_voters: Some(5_479_123),
+            participation: Some(Decimal::new(5910, 4)),
+            cantons_voting_yes: Tristate::Value(Decimal::new(35, 1)),
+            ..Default::default()
+        });
+        let df = create_measures_long(&data);
+        let measures: Vec<_> = df
+            .column(MEASURE)
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let values: Vec<_> = df
+            .column(VALUE)
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(
+            measures,
+            vec![TOTAL_VOTERS, PARTICIPATION, CANTONS_VOTING_YES]
+        );
+        assert_eq!(values, vec!["5479123", "0.5910", "3.5"]);
+    }
+
+    #[test]
+    fn shares_of_a_canton_add_up_to_one() {
+        let cantons = AreaResults {
+            no: vec![631],
+            canton_number: vec!["1".to_string()],
+            canton: vec!["Zürich".to_string()],
+            district_number: vec![None],
+            number: vec!["1".to_string()],
+            name: vec!["Zürich".to_string()],
+            counted: vec![Some(true)],
+            total_voters: vec![Some(951_221)],
+            ballots_returned: vec![Some(473_480)],
+            valid_voting_ballots: vec![Some(466_859)],
+            total_yes: vec![Some(251_047)],
+            total_no: vec![Some(215_812)],
+            ratio_yes: vec![None],
+            participation: vec![None],
+        };
+        let df = create_canton_answers_long(&cantons);
+        let shares: Vec<f64> = df
+            .column(SHARE)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(shares.len(), 2);
+        assert!((shares.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn shares_of_a_voting_add_up_to_one() {
+        let mut data = Data::default();
+        data.update(Row {
+            no: Some(631),
+            ballots_returned: Some(3_261_344),
+            total_yes: Some(1_233_995),
+            total_no: Some(1_988_349),
+            blank_voting_ballots: Some(31_204),
+            invalid_voting_ballots: Some(7_796),
+            ..Default::default()
+        });
+        let df = create_answers_long(&data);
+        let shares: Vec<f64> = df
+            .column(SHARE)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(shares.len(), 4);
+        assert!((shares.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+}
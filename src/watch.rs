@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+use std::process;
+
+use chrono::{Duration, NaiveDate, Utc};
+use log::{info, warn};
+use serde_json::json;
+
+use crate::constants::DATASET_FILE;
+use crate::data::{Data, ResultStatus, Row, VotingStatus};
+use crate::diff::diff_datasets;
+use crate::extractors::{
+    clear_page_cache, extract_new_or_changed_votings, extract_scheduled_votings,
+};
+
+#[derive(Default)]
+pub struct Changes {
+    pub added: Vec<u32>,
+    pub updated: Vec<u32>,
+    // Scheduled votings added or updated before they got a number
+    pub without_number: usize,
+    // Scheduled votings dropped once their date had passed
+    pub removed: usize,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.without_number == 0
+            && self.removed == 0
+    }
+
+    fn record(&mut self, no: Option<u32>, added: bool) {
+        match (no, added) {
+            (Some(no), true) => self.added.push(no),
+            (Some(no), false) => self.updated.push(no),
+            (None, _) => self.without_number += 1,
+        }
+    }
+}
+
+pub fn poll_votings(
+    data: &mut Data,
+    recheck_days: i64,
+    upcoming: bool,
+) -> reqwest::Result<Changes> {
+    // Scheduled votings are not known, hence extracted as soon as their results are published
+    let mut known_votings: HashMap<u32, String> = HashMap::new();
+    let mut provisional_votings: HashSet<u32> = HashSet::new();
+    for (idx, no) in data.no.iter().enumerate() {
+        if let (Some(no), VotingStatus::Completed) = (no, data.status[idx]) {
+            known_votings.insert(*no, data.content_sha256[idx].clone());
+            if data.result_status[idx] == Some(ResultStatus::Provisional) {
+                provisional_votings.insert(*no);
+            }
+        }
+    }
+
+    let recheck_since = Utc::now().date_naive() - Duration::days(recheck_days);
+    // Every page is fetched before the dataset is changed, so that a failed poll changes nothing
+    let extracted =
+        extract_new_or_changed_votings(&known_votings, &provisional_votings, recheck_since)
+            .and_then(|extracted| match upcoming {
+                true => Ok((extracted, Some(extract_scheduled_votings()?))),
+                false => Ok((extracted, None)),
+            });
+    // Every poll has to see the pages as they are now, and the watch must not keep them all
+    clear_page_cache();
+    let (extracted, scheduled) = extracted?;
+
+    let mut changes = Changes::default();
+    merge_completed_votings(data, extracted, &mut changes);
+    if let Some(scheduled) = scheduled {
+        merge_scheduled_votings(data, scheduled, &mut changes);
+    }
+    drop_past_scheduled_votings(data, Utc::now().date_naive(), &mut changes);
+    Ok(changes)
+}
+
+// Adds the new votings and replaces those whose results changed, or that were scheduled
+fn merge_completed_votings(data: &mut Data, extracted: Data, changes: &mut Changes) {
+    for idx in 0..extracted.no.len() {
+        let row = extracted.row(idx);
+        let no = row.no;
+        match data.position_of(&row) {
+            Some(existing_idx) => {
+                // A page whose hash changed without any value, e.g. a new navigation, is
+                // replaced for its provenance but not reported as updated
+                let updated = match data.status[existing_idx] {
+                    VotingStatus::Scheduled => {
+                        info!("Voting '{}' is completed", row.title_it);
+                        true
+                    }
+                    VotingStatus::Completed => log_changed_values(&data.row(existing_idx), &row),
+                };
+                data.replace(existing_idx, row);
+                if updated {
+                    changes.record(no, false);
+                }
+            }
+            None => {
+                data.update(row);
+                changes.record(no, true);
+            }
+        }
+    }
+}
+
+// Scheduled votings still in the dataset after their date were not matched by their results,
+// e.g. because the object was withdrawn, and are dropped. Votings of today wait for their
+// results.
+pub fn drop_past_scheduled_votings(data: &mut Data, today: NaiveDate, changes: &mut Changes) {
+    let is_past = |row: &Row| {
+        row.status == VotingStatus::Scheduled && row.date_of_voting.is_some_and(|date| date < today)
+    };
+    for idx in 0..data.no.len() {
+        let row = data.row(idx);
+        if is_past(&row) {
+            info!(
+                "Scheduled voting '{}' of {} dropped, its date has passed",
+                row.title_it,
+                row.date_of_voting.unwrap()
+            );
+            changes.removed += 1;
+        }
+    }
+    data.retain(|row| !is_past(row));
+}
+
+// Changes of provisional results are reported by voting and column, e.g. "Voting 612:
+// total_yes changed from 1000 to 1010", and so is the change of status once they are final
+// Whether a value of the voting changed. Votings are compared on their number, so a voting
+// without one is taken as changed.
+fn log_changed_values(old: &Row, new: &Row) -> bool {
+    if old.no.is_none() || new.no.is_none() {
+        return true;
+    }
+    let mut old_data = Data::default();
+    old_data.update(old.clone());
+    let mut new_data = Data::default();
+    new_data.update(new.clone());
+
+    let changes = diff_datasets(&old_data, &new_data, 0.0).changes;
+    for change in &changes {
+        info!(
+            "Voting {}: {} changed from {} to {}",
+            change.no,
+            change.column,
+            change.old.as_deref().unwrap_or("null"),
+            change.new.as_deref().unwrap_or("null")
+        );
+    }
+    !changes.is_empty()
+}
+
+// Adds the scheduled votings that are not in the dataset yet and updates those whose row on
+// the page of upcoming votings changed. Completed votings are never turned back to scheduled.
+pub fn merge_scheduled_votings(data: &mut Data, scheduled: Data, changes: &mut Changes) {
+    for idx in 0..scheduled.no.len() {
+        let row = scheduled.row(idx);
+        let no = row.no;
+        match data.position_of(&row) {
+            Some(existing_idx) => {
+                if data.status[existing_idx] == VotingStatus::Scheduled
+                    && data.content_sha256[existing_idx] != row.content_sha256
+                {
+                    data.replace(existing_idx, row);
+                    changes.record(no, false);
+                }
+            }
+            None => {
+                data.update(row);
+                changes.record(no, true);
+            }
+        }
+    }
+}
+
+fn numbers_to_string(numbers: &[u32]) -> String {
+    numbers
+        .iter()
+        .map(|no| no.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn notify(changes: &Changes, on_change: Option<&str>, webhook: Option<&str>) {
+    // A failing hook must not stop the watch, hence errors are only logged
+    if let Some(command) = on_change {
+        let status = process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("VOTINGS_ADDED", numbers_to_string(&changes.added))
+            .env("VOTINGS_UPDATED", numbers_to_string(&changes.updated))
+            .env("VOTINGS_WITHOUT_NUMBER", changes.without_number.to_string())
+            .env("VOTINGS_REMOVED", changes.removed.to_string())
+            .env("DATASET", DATASET_FILE)
+            .status();
+        match status {
+            Ok(status) if status.success() => info!("Hook '{}' completed", command),
+            Ok(status) => warn!("Hook '{}' failed with {}", command, status),
+            Err(error) => warn!("Hook '{}' could not be run: {}", command, error),
+        }
+    }
+
+    if let Some(url) = webhook {
+        let body = json!({
+            "dataset": DATASET_FILE,
+            "added": changes.added,
+            "updated": changes.updated,
+            "without_number": changes.without_number,
+            "removed": changes.removed,
+        });
+        let response = reqwest::blocking::Client::new()
+            .post(url)
+            .json(&body)
+            .send()
+            .and_then(|response| response.error_for_status());
+        match response {
+            Ok(_) => info!("Webhook {} notified", url),
+            Err(error) => warn!("Webhook {} could not be notified: {}", url, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voting(no: Option<u32>, date: (i32, u32, u32), title_it: &str, status: VotingStatus) -> Row {
+        Row {
+            no,
+            date_of_voting: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            title_it: title_it.to_string(),
+            status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn completed_votings_replace_their_scheduled_row_with_another_title() {
+        let mut data = Data::default();
+        data.update(voting(
+            None,
+            (2020, 9, 27),
+            "Iniziativa popolare «Per un'immigrazione moderata»",
+            VotingStatus::Scheduled,
+        ));
+        data.update(voting(
+            None,
+            (2020, 9, 27),
+            "Legge federale sulla caccia",
+            VotingStatus::Scheduled,
+        ));
+
+        let mut extracted = Data::default();
+        extracted.update(voting(
+            Some(631),
+            (2020, 9, 27),
+            "Iniziativa popolare «Per un'immigrazione moderata (Iniziativa per la limitazione)»",
+            VotingStatus::Completed,
+        ));
+        let mut changes = Changes::default();
+        merge_completed_votings(&mut data, extracted, &mut changes);
+
+        assert_eq!(data.no, vec![Some(631), None]);
+        assert_eq!(
+            data.status,
+            vec![VotingStatus::Completed, VotingStatus::Scheduled]
+        );
+        assert_eq!(changes.updated, vec![631]);
+    }
+
+    #[test]
+    fn votings_with_another_number_are_not_matched_on_their_title() {
+        let mut data = Data::default();
+        data.update(voting(
+            Some(630),
+            (2020, 9, 27),
+            "Legge federale sulla caccia",
+            VotingStatus::Completed,
+        ));
+        let mut extracted = Data::default();
+        extracted.update(voting(
+            Some(632),
+            (2020, 9, 27),
+            "Legge federale sulla caccia",
+            VotingStatus::Completed,
+        ));
+        let mut changes = Changes::default();
+        merge_completed_votings(&mut data, extracted, &mut changes);
+
+        assert_eq!(data.no, vec![Some(630), Some(632)]);
+        assert_eq!(changes.added, vec![632]);
+    }
+
+    #[test]
+    fn a_new_hash_without_changed_values_is_not_an_update() {
+        let completed = |content_sha256: &str, total_yes: u32| Row {
+            content_sha256: content_sha256.to_string(),
+            total_yes: Some(total_yes),
+            ..voting(
+                Some(631),
+                (2020, 9, 27),
+                "Legge federale sulla caccia",
+                VotingStatus::Completed,
+            )
+        };
+        let mut data = Data::default();
+        data.update(completed("old", 1_233_995));
+
+        let mut extracted = Data::default();
+        extracted.update(completed("new", 1_233_995));
+        let mut changes = Changes::default();
+        merge_completed_votings(&mut data, extracted, &mut changes);
+        assert!(changes.is_empty());
+        assert_eq!(data.content_sha256, vec!["new"]);
+
+        let mut extracted = Data::default();
+        extracted.update(completed("newer", 1_233_996));
+        merge_completed_votings(&mut data, extracted, &mut changes);
+        assert_eq!(changes.updated, vec![631]);
+    }
+
+    #[test]
+    fn scheduled_votings_are_dropped_once_their_date_has_passed() {
+        let mut data = Data::default();
+        data.update(voting(
+            Some(631),
+            (2020, 9, 27),
+            "Completed",
+            VotingStatus::Completed,
+        ));
+        data.update(voting(
+            None,
+            (2020, 9, 27),
+            "Withdrawn",
+            VotingStatus::Scheduled,
+        ));
+        data.update(voting(
+            None,
+            (2020, 11, 29),
+            "Today",
+            VotingStatus::Scheduled,
+        ));
+        data.update(voting(None, (2021, 3, 7), "Next", VotingStatus::Scheduled));
+
+        let mut changes = Changes::default();
+        drop_past_scheduled_votings(
+            &mut data,
+            NaiveDate::from_ymd_opt(2020, 11, 29).unwrap(),
+            &mut changes,
+        );
+
+        assert_eq!(data.title_it, vec!["Completed", "Today", "Next"]);
+        assert_eq!(changes.removed, 1);
+        assert!(!changes.is_empty());
+    }
+}